@@ -2,11 +2,20 @@ use std::path::PathBuf;
 
 use argh::FromArgs;
 
+use crate::docgen::Visibility;
+use crate::report::Format;
+
 // Command line argument parsing using argh
 
 #[derive(FromArgs, Debug)]
 /// VexDoc - A fast, simple documentation generator for any programming language
 pub struct VexDocArgs {
+    #[argh(option)]
+    /// path to the VexDoc.toml to use (defaults to the nearest one in this directory or a parent)
+    pub config: Option<PathBuf>,
+    #[argh(switch)]
+    /// print internal error details, for bug reports
+    pub debug: bool,
     #[argh(subcommand)]
     pub subcommands: VexDocSubcommands,
 }
@@ -17,6 +26,8 @@ pub struct VexDocArgs {
 pub enum VexDocSubcommands {
     Init(InitArgs),
     Generate(GenArgs),
+    Check(CheckArgs),
+    Explain(ExplainArgs),
 }
 
 #[derive(FromArgs, Debug)]
@@ -26,7 +37,36 @@ pub struct InitArgs {
     #[argh(option, default = "\".\".into()")]
     /// directory where the config file should be created (defaults to current directory)
     pub dir: PathBuf,
-    // TODO: Add overwrite flag
+    #[argh(option)]
+    /// comma-separated built-in language presets to fill the config with, e.g. rust,python
+    pub preset: Option<String>,
+    #[argh(switch)]
+    /// scan the directory and configure every language and build directory found in it
+    pub detect: bool,
+    #[argh(switch, short = 'f')]
+    /// overwrite an existing config file
+    pub force: bool,
+}
+
+#[derive(FromArgs, Debug)]
+/// Check annotations for errors without writing any documentation
+#[argh(subcommand, name = "check")]
+pub struct CheckArgs {
+    #[argh(option)]
+    /// specific files to check (if not provided, checks all matching files)
+    pub files: Vec<PathBuf>,
+    #[argh(option, default = "Format::Human")]
+    /// how to print problems: human, json, sarif, junit or github (defaults to human)
+    pub format: Format,
+}
+
+#[derive(FromArgs, Debug)]
+/// Explain an error code in detail, with an example
+#[argh(subcommand, name = "explain")]
+pub struct ExplainArgs {
+    #[argh(positional)]
+    /// the code printed next to an error, e.g. VD0007 (lists every code if left out)
+    pub code: Option<String>,
 }
 
 #[derive(FromArgs, Debug)]
@@ -36,6 +76,12 @@ pub struct GenArgs {
     #[argh(option)]
     /// specific files to process (if not provided, processes all matching files)
     pub files: Vec<PathBuf>,
+    #[argh(option)]
+    /// directory to write the HTML files to, overriding output_dir from the config
+    pub out_dir: Option<PathBuf>,
+    #[argh(option, default = "Visibility::Public")]
+    /// which sections to write: public, internal or all (defaults to public)
+    pub visibility: Visibility,
     #[argh(switch, short = 'v')]
     /// show detailed progress information for each file
     pub verbose: bool,
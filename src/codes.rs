@@ -0,0 +1,387 @@
+/*filesummary
+Every distinct failure has a stable `VDxxxx` code, shown next to the error and explained at length by `vexdoc explain <code>`.
+Codes are never reused or renumbered, so they are safe to search for and to mention in scripts.
+endsummary*/
+
+use std::fmt;
+
+/// A stable identifier for one kind of failure
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ErrorCode {
+    ConfigExists,
+    ConfigNotCreated,
+    FileUnreadable,
+    GenerationFailed,
+    OutputNotWritten,
+    ConfigSyntax,
+    NoCommentDelimiters,
+    IncompletePair,
+    NoFileExtensions,
+    LeadingPeriod,
+    DuplicateExtension,
+    InvalidGlob,
+    EmptyMarker,
+    MissingSourceRoot,
+    UnknownPreset,
+    NothingDetected,
+    UnsupportedFileType,
+    UnterminatedLineSummary,
+    UnterminatedFileSummary,
+    UnterminatedSection,
+    UnknownCode,
+    EmptySummary,
+    EmptyCode,
+    DuplicateTitle,
+    LongTitle,
+    SummaryPunctuation,
+    NoSections,
+    LongCode,
+    UnknownTag,
+    UnresolvedReference,
+    UnknownEncoding,
+    LossyDecoding,
+    UnterminatedString,
+    UnknownLint,
+}
+
+/// The long-form documentation of an error code
+pub struct Explanation {
+    pub code: ErrorCode,
+    /// One line describing the failure
+    pub title: &'static str,
+    pub explanation: &'static str,
+    /// An example that triggers the error, and the same example fixed
+    pub incorrect: &'static str,
+    pub correct: &'static str,
+}
+
+impl ErrorCode {
+    /// The code as shown to users, e.g. `VD0007`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::ConfigExists => "VD0001",
+            Self::ConfigNotCreated => "VD0002",
+            Self::FileUnreadable => "VD0003",
+            Self::GenerationFailed => "VD0004",
+            Self::OutputNotWritten => "VD0005",
+            Self::ConfigSyntax => "VD0006",
+            Self::NoCommentDelimiters => "VD0007",
+            Self::IncompletePair => "VD0008",
+            Self::NoFileExtensions => "VD0009",
+            Self::LeadingPeriod => "VD0010",
+            Self::DuplicateExtension => "VD0011",
+            Self::InvalidGlob => "VD0012",
+            Self::EmptyMarker => "VD0013",
+            Self::MissingSourceRoot => "VD0014",
+            Self::UnknownPreset => "VD0015",
+            Self::NothingDetected => "VD0016",
+            Self::UnsupportedFileType => "VD0017",
+            Self::UnterminatedLineSummary => "VD0018",
+            Self::UnterminatedFileSummary => "VD0019",
+            Self::UnterminatedSection => "VD0020",
+            Self::UnknownCode => "VD0021",
+            Self::EmptySummary => "VD0022",
+            Self::EmptyCode => "VD0023",
+            Self::DuplicateTitle => "VD0024",
+            Self::LongTitle => "VD0025",
+            Self::SummaryPunctuation => "VD0026",
+            Self::NoSections => "VD0027",
+            Self::LongCode => "VD0028",
+            Self::UnknownTag => "VD0029",
+            Self::UnresolvedReference => "VD0030",
+            Self::UnknownEncoding => "VD0031",
+            Self::LossyDecoding => "VD0032",
+            Self::UnterminatedString => "VD0033",
+            Self::UnknownLint => "VD0034",
+        }
+    }
+
+    /// Looks up a code, accepting `VD0007`, `vd0007`, `vd7` and `7`
+    pub fn parse(code: &str) -> Option<ErrorCode> {
+        let code = code.trim();
+        let digits = code
+            .strip_prefix("VD")
+            .or_else(|| code.strip_prefix("vd"))
+            .unwrap_or(code);
+        let number: usize = digits.parse().ok()?;
+        EXPLANATIONS
+            .iter()
+            .map(|e| e.code)
+            .find(|c| c.as_str()[2..].parse::<usize>() == Ok(number))
+    }
+
+    pub fn explanation(self) -> &'static Explanation {
+        EXPLANATIONS
+            .iter()
+            .find(|e| e.code == self)
+            .expect("every error code has an explanation")
+    }
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let indent = |example: &str| {
+            example
+                .lines()
+                .map(|line| format!("    {}", line))
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        write!(
+            f,
+            "{}: {}\n\n{}\n\nIncorrect:\n{}\n\nCorrect:\n{}",
+            self.code,
+            self.title,
+            self.explanation,
+            indent(self.incorrect),
+            indent(self.correct)
+        )
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+pub const EXPLANATIONS: &[Explanation] = &[
+    Explanation {
+        code: ErrorCode::ConfigExists,
+        title: "a VexDoc.toml already exists",
+        explanation: "`vexdoc init` refuses to overwrite an existing config file, so hand-made changes are not lost by accident.",
+        incorrect: "vexdoc init --preset rust   # VexDoc.toml is already there",
+        correct: "vexdoc init --preset rust --force",
+    },
+    Explanation {
+        code: ErrorCode::ConfigNotCreated,
+        title: "the config file could not be created",
+        explanation: "`vexdoc init` could not write VexDoc.toml, usually because the directory does not exist or is not writable.",
+        incorrect: "vexdoc init --dir does/not/exist",
+        correct: "mkdir -p project && vexdoc init --dir project",
+    },
+    Explanation {
+        code: ErrorCode::FileUnreadable,
+        title: "a file could not be read",
+        explanation: "A config or source file could not be read. Check that it exists and that you have permission to read it.",
+        incorrect: "vexdoc --config missing/VexDoc.toml generate",
+        correct: "vexdoc --config project/VexDoc.toml generate",
+    },
+    Explanation {
+        code: ErrorCode::GenerationFailed,
+        title: "documentation generation failed",
+        explanation: "Something outside the sources and config went wrong while generating, such as the output directory not being creatable.",
+        incorrect: "output_dir = \"/root/docs\"   # not writable for this user",
+        correct: "output_dir = \"target/vexdoc\"",
+    },
+    Explanation {
+        code: ErrorCode::OutputNotWritten,
+        title: "a generated page could not be written",
+        explanation: "A page was generated but could not be saved in the output directory. Check that it is writable and that the disk is not full.",
+        incorrect: "chmod -w docs && vexdoc generate",
+        correct: "chmod +w docs && vexdoc generate",
+    },
+    Explanation {
+        code: ErrorCode::ConfigSyntax,
+        title: "VexDoc.toml is not valid TOML or is missing a required key",
+        explanation: "The config file could not be read as TOML, or a required key such as `ignored_dirs` or `file_extensions` is missing or has the wrong type.",
+        incorrect: "file_extensions = \"rs\"",
+        correct: "ignored_dirs = []\nfile_extensions = [\"rs\"]",
+    },
+    Explanation {
+        code: ErrorCode::NoCommentDelimiters,
+        title: "no comment delimiters are configured",
+        explanation: "VexDoc needs at least one kind of comment, line or block, to find annotations. Set `inline_comments`, `multi_comments` or both, at the top level or in a `[languages.<name>]` table.",
+        incorrect: "[languages.sh]\nextensions = [\"sh\"]",
+        correct: "[languages.sh]\nextensions = [\"sh\"]\ninline_comments = \"#\"",
+    },
+    Explanation {
+        code: ErrorCode::IncompletePair,
+        title: "a block comment delimiter has no partner",
+        explanation: "Each entry of `multi_comments` needs both an opening and a closing delimiter, otherwise VexDoc cannot tell where a summary ends.",
+        incorrect: "multi_comments = [\"/*\"]",
+        correct: "multi_comments = [\"/*\", \"*/\"]",
+    },
+    Explanation {
+        code: ErrorCode::NoFileExtensions,
+        title: "no file extensions are configured",
+        explanation: "Without `file_extensions` or a `[languages.<name>]` table, there are no files to document.",
+        incorrect: "file_extensions = []",
+        correct: "file_extensions = [\"rs\"]",
+    },
+    Explanation {
+        code: ErrorCode::LeadingPeriod,
+        title: "a file extension starts with a period",
+        explanation: "Extensions are compared without their leading period, so `.rs` never matches anything.",
+        incorrect: "file_extensions = [\".rs\"]",
+        correct: "file_extensions = [\"rs\"]",
+    },
+    Explanation {
+        code: ErrorCode::DuplicateExtension,
+        title: "an extension belongs to more than one language",
+        explanation: "Each extension can only be documented with one comment syntax, so it may appear in only one `[languages.<name>]` table.",
+        incorrect: "[languages.c]\nextensions = [\"c\", \"h\"]\n\n[languages.cpp]\nextensions = [\"cpp\", \"h\"]",
+        correct: "[languages.c]\nextensions = [\"c\", \"h\"]\n\n[languages.cpp]\nextensions = [\"cpp\"]",
+    },
+    Explanation {
+        code: ErrorCode::InvalidGlob,
+        title: "an include or exclude pattern is not a valid glob",
+        explanation: "`include` and `exclude` take gitignore-style globs. Braces must be closed, ranges like `[a-z]` must run from low to high, and a pattern cannot end with a lone `\\`.",
+        incorrect: "exclude = [\"src/{generated,vendored\"]",
+        correct: "exclude = [\"src/generated/**\"]",
+    },
+    Explanation {
+        code: ErrorCode::EmptyMarker,
+        title: "an annotation marker is empty",
+        explanation: "The keywords in a `[markers]` table cannot be empty, or every comment would match them. Remove the key to use the default.",
+        incorrect: "[markers]\nend = \"\"",
+        correct: "[markers]\nend = \"END\"",
+    },
+    Explanation {
+        code: ErrorCode::MissingSourceRoot,
+        title: "a source root does not exist",
+        explanation: "Every directory in `source_roots` must exist, relative to the directory holding VexDoc.toml.",
+        incorrect: "source_roots = [\"sources\"]   # the directory is called src",
+        correct: "source_roots = [\"src\"]",
+    },
+    Explanation {
+        code: ErrorCode::UnknownPreset,
+        title: "an unknown preset was requested",
+        explanation: "`vexdoc init --preset` only knows the built-in presets, named by language or by file extension.",
+        incorrect: "vexdoc init --preset brainfuck",
+        correct: "vexdoc init --preset rust,python",
+    },
+    Explanation {
+        code: ErrorCode::NothingDetected,
+        title: "no known languages were detected",
+        explanation: "`vexdoc init --detect` found no files with a comment syntax it knows. Name the languages with `--preset` instead.",
+        incorrect: "vexdoc init --detect   # in an empty directory",
+        correct: "vexdoc init --preset rust",
+    },
+    Explanation {
+        code: ErrorCode::UnsupportedFileType,
+        title: "no comment syntax is configured for a file",
+        explanation: "A file was selected for documentation, but neither a `[languages.<name>]` table nor the top-level settings say how its comments look.",
+        incorrect: "file_extensions = [\"rs\", \"py\"]\ninline_comments = \"//\"\n\n[languages.rs]\ninline_comments = \"//\"",
+        correct: "file_extensions = [\"rs\", \"py\"]\n\n[languages.rs]\ninline_comments = \"//\"\n\n[languages.py]\ninline_comments = \"#\"",
+    },
+    Explanation {
+        code: ErrorCode::UnterminatedLineSummary,
+        title: "a summary written as line comments is not closed",
+        explanation: "A summary made of line comments must end with a comment holding `endsummary` before the code starts.",
+        incorrect: "#! Upload\n# startsummary\n# Copies the build.\nrsync -a build/ server:/srv\n# ENDVEXDOC",
+        correct: "#! Upload\n# startsummary\n# Copies the build.\n# endsummary\nrsync -a build/ server:/srv\n# ENDVEXDOC",
+    },
+    Explanation {
+        code: ErrorCode::UnterminatedFileSummary,
+        title: "the file summary is never closed",
+        explanation: "The file ended before the `endsummary` that closes its file summary. Add it, or set `allow_unterminated = true` to keep the partial summary.",
+        incorrect: "/*filesummary\nUtilities for parsing.\n",
+        correct: "/*filesummary\nUtilities for parsing.\nendsummary*/\n",
+    },
+    Explanation {
+        code: ErrorCode::UnterminatedSection,
+        title: "a section never ends",
+        explanation: "The file ended while a section was still open, usually because its `ENDVEXDOC` line is missing. Add it, set `allow_unterminated = true` to keep the partial section, or set `block_end` so sections end without one.",
+        incorrect: "//! Parse\n/*startsummary\nParses input.\nendsummary*/\nfn parse() {}\n",
+        correct: "//! Parse\n/*startsummary\nParses input.\nendsummary*/\nfn parse() {}\n// ENDVEXDOC\n",
+    },
+    Explanation {
+        code: ErrorCode::UnknownCode,
+        title: "an unknown error code was given to `vexdoc explain`",
+        explanation: "`vexdoc explain` takes a code as printed next to an error, with or without the `VD` prefix and leading zeros.",
+        incorrect: "vexdoc explain VD9999",
+        correct: "vexdoc explain VD0007",
+    },
+    Explanation {
+        code: ErrorCode::EmptySummary,
+        title: "a summary is empty (lint `empty_summary`)",
+        explanation: "A summary was opened and closed with nothing in between, so the page shows an empty paragraph. Write the summary, or remove its markers.",
+        incorrect: "//! Parse\n/*startsummary\nendsummary*/\nfn parse() {}\n// ENDVEXDOC",
+        correct: "//! Parse\n/*startsummary\nParses the input.\nendsummary*/\nfn parse() {}\n// ENDVEXDOC",
+    },
+    Explanation {
+        code: ErrorCode::EmptyCode,
+        title: "a section has no code (lint `empty_code`)",
+        explanation: "The end marker of a section comes right after its title or summary, usually because it was placed above the code instead of below it.",
+        incorrect: "//! Parse\n// ENDVEXDOC\nfn parse() {}",
+        correct: "//! Parse\nfn parse() {}\n// ENDVEXDOC",
+    },
+    Explanation {
+        code: ErrorCode::DuplicateTitle,
+        title: "two sections in a file have the same title (lint `duplicate_title`)",
+        explanation: "Sections are told apart by their titles, so each title should appear only once per file.",
+        incorrect: "//! Parse\nfn parse() {}\n// ENDVEXDOC\n//! Parse\nfn parse_all() {}\n// ENDVEXDOC",
+        correct: "//! Parse\nfn parse() {}\n// ENDVEXDOC\n//! Parse All\nfn parse_all() {}\n// ENDVEXDOC",
+    },
+    Explanation {
+        code: ErrorCode::LongTitle,
+        title: "a title is too long (lint `long_title`)",
+        explanation: "A title is longer than `max_title_length` in the `[lints]` table, 80 characters by default. Keep titles short and put the details in the summary.",
+        incorrect: "//! Parse the input, which may be a file or standard input, and return the syntax tree",
+        correct: "//! Parse\n/*startsummary\nParses a file or standard input and returns the syntax tree.\nendsummary*/",
+    },
+    Explanation {
+        code: ErrorCode::SummaryPunctuation,
+        title: "a summary does not end with punctuation (lint `summary_punctuation`)",
+        explanation: "A summary should end a sentence with `.`, `!`, `?` or `:`. This rule is off unless enabled in the `[lints]` table.",
+        incorrect: "/*startsummary\nParses the input\nendsummary*/",
+        correct: "/*startsummary\nParses the input.\nendsummary*/",
+    },
+    Explanation {
+        code: ErrorCode::NoSections,
+        title: "a file has a file summary but no sections (lint `no_sections`)",
+        explanation: "The file is described, but none of its code is documented, often because the titles are missing or use the wrong marker.",
+        incorrect: "/*filesummary\nHelpers for parsing.\nendsummary*/\nfn parse() {}",
+        correct: "/*filesummary\nHelpers for parsing.\nendsummary*/\n//! Parse\nfn parse() {}\n// ENDVEXDOC",
+    },
+    Explanation {
+        code: ErrorCode::LongCode,
+        title: "a section has too much code (lint `long_code`)",
+        explanation: "A section has more lines of code than `max_code_lines` in the `[lints]` table, 100 by default. Split it into smaller sections. This rule is off unless enabled.",
+        incorrect: "[lints]\nlong_code = \"warn\"\nmax_code_lines = 5   # with a 40 line section",
+        correct: "[lints]\nlong_code = \"warn\"\nmax_code_lines = 50",
+    },
+    Explanation {
+        code: ErrorCode::UnknownTag,
+        title: "a summary has an unknown tag (lint `unknown_tag`)",
+        explanation: "Summaries can hold the tags @param, @returns, @errors, @example, @see, @since, @deprecated, @internal and @public. Any other `@name` at the start of a line is shown as written, without the formatting of a known tag, and is usually a misspelling.",
+        incorrect: "/*startsummary\nParses the input.\n@parm input the text to parse\nendsummary*/",
+        correct: "/*startsummary\nParses the input.\n@param input the text to parse\nendsummary*/",
+    },
+    Explanation {
+        code: ErrorCode::UnresolvedReference,
+        title: "a reference matches no section (lint `unresolved_reference`)",
+        explanation: "`[[Title]]` links to the section with that title in the same file, and `[[path/to/file.rs#Title]]` to one in another file, with the path relative to the project root. A reference whose file was not documented or has no section with that exact title is shown as plain text.",
+        incorrect: "/*startsummary\nSee [[Parse Input]].\nendsummary*/",
+        correct: "/*startsummary\nSee [[src/parser.rs#Parse Input]].\nendsummary*/",
+    },
+    Explanation {
+        code: ErrorCode::UnknownEncoding,
+        title: "the `encoding` is not a known encoding",
+        explanation: "`encoding` names the encoding of source files that are not valid UTF-8, using a WHATWG label such as \"windows-1252\", \"latin1\", \"shift_jis\" or \"utf-16le\".",
+        incorrect: "encoding = \"ansi\"",
+        correct: "encoding = \"windows-1252\"",
+    },
+    Explanation {
+        code: ErrorCode::LossyDecoding,
+        title: "a source file has bytes that are not valid text",
+        explanation: "The file has no byte order mark and is not valid UTF-8 or UTF-16, and no `encoding` is set to read it in, or it is not valid in that encoding either. Its documentation is still generated, with each invalid byte shown as U+FFFD. Set `encoding` to the encoding the file is saved in, or convert it to UTF-8.",
+        incorrect: "# a Latin-1 file, with no encoding in VexDoc.toml",
+        correct: "encoding = \"latin1\"",
+    },
+    Explanation {
+        code: ErrorCode::UnterminatedString,
+        title: "a string literal is still open at the end of a file",
+        explanation: "Markers inside the `multiline_strings` configured for a language are read as code. A string that never closes therefore hides every marker after it, which usually means `multiline_strings` doesn't match how the language writes strings, e.g. a raw string without a pair of its own.",
+        incorrect: "multiline_strings = [\"\\\"\", \"\\\"\"]\n\n# in the source: const S: &str = r#\"say \"hi\"#;",
+        correct: "multiline_strings = [[\"\\\"\", \"\\\"\"], [\"r#\\\"\", \"\\\"#\"]]",
+    },
+    Explanation {
+        code: ErrorCode::UnknownLint,
+        title: "`vexdoc-allow` names a rule that does not exist",
+        explanation: "A `vexdoc-allow(rule)` directive only turns off the rules it names exactly, so a misspelt name allows nothing. Rule names are written in snake_case, as in the `[lints]` table.",
+        incorrect: "// vexdoc-allow(empty-summary)",
+        correct: "// vexdoc-allow(empty_summary)",
+    },
+];
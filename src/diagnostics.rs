@@ -0,0 +1,162 @@
+/*filesummary
+Renders errors for the terminal: the severity and kind, where the problem is, the offending line with a caret under it, and how to fix it.
+endsummary*/
+
+use std::env;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::Path;
+
+use encoding_rs::Encoding;
+
+use crate::docgen::encoding::decode;
+use crate::errors::{Span, SubcommandError};
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const CYAN: &str = "\x1b[1;36m";
+const YELLOW: &str = "\x1b[1;33m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// Formats errors, optionally with ANSI colours
+#[derive(Debug, Clone, Copy)]
+pub struct Renderer {
+    color: bool,
+    /// The configured `encoding`, for showing sources that are neither UTF-8 nor UTF-16
+    encoding: Option<&'static Encoding>,
+}
+
+impl Renderer {
+    pub fn new(color: bool) -> Self {
+        Renderer { color, encoding: None }
+    }
+
+    /// Reads the sources shown in code frames the way generation does, with `encoding` as the fallback
+    pub fn with_encoding(mut self, encoding: Option<&'static Encoding>) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Uses colour only when stderr is a terminal and `NO_COLOR` is unset or empty
+    pub fn for_stderr() -> Self {
+        let no_color = env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        Renderer::new(!no_color && io::stderr().is_terminal())
+    }
+
+    fn paint(&self, style: &str, text: &str) -> String {
+        if self.color {
+            format!("{}{}{}", style, text, RESET)
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// Renders an error, and every error inside a collected one, ending with a newline
+    pub fn render(&self, err: &SubcommandError) -> String {
+        // Every error except a collection has a code, e.g. `error[VD0007]`
+        let severity = if err.is_warning() { "warning" } else { "error" };
+        let label = match err.code() {
+            Some(code) => format!("{}[{}]", severity, code),
+            None => severity.to_string(),
+        };
+        match err {
+            // Printed already, e.g. as a machine-readable report
+            SubcommandError::Reported(_) => String::new(),
+            SubcommandError::Multiple(errors) => {
+                let mut out = String::new();
+                for error in errors {
+                    out.push_str(&self.render(error));
+                    out.push('\n');
+                }
+                out.push_str(&self.headline("error", &err.message()));
+                out
+            }
+            SubcommandError::UserError {
+                causes,
+                file,
+                span,
+                help,
+                ..
+            } => {
+                // The gutter is as wide as the line number shown in the code frame
+                let gutter = " ".repeat(span.map_or(1, |s| s.line.to_string().len()));
+                let mut lines = causes.lines();
+                let mut out = self.headline(&label, lines.next().unwrap_or_default());
+                out.push_str(&self.frame(file, *span, &gutter, err.is_warning()));
+                for note in lines.filter(|l| !l.trim().is_empty()) {
+                    out.push_str(&self.note(&gutter, "note", note));
+                }
+                if let Some(help) = help {
+                    out.push_str(&self.note(&gutter, "help", help));
+                }
+                out
+            }
+            _ => {
+                let mut out = self.headline(&label, &err.message());
+                if let Some(help) = err.help() {
+                    out.push_str(&self.note(" ", "help", help));
+                }
+                out
+            }
+        }
+    }
+
+    fn headline(&self, label: &str, message: &str) -> String {
+        let style = if label.starts_with("warning") { YELLOW } else { RED };
+        format!("{}: {}\n", self.paint(style, label), self.paint(BOLD, message))
+    }
+
+    fn note(&self, gutter: &str, label: &str, message: &str) -> String {
+        format!("{} {} {}: {}\n", gutter, self.paint(BLUE, "="), self.paint(CYAN, label), message)
+    }
+
+    /// The `--> path:line:col` pointer, followed by the offending line and a caret when it can be read
+    fn frame(&self, file: &Path, span: Option<Span>, gutter: &str, warning: bool) -> String {
+        // Errors about the command line itself have no file to point at
+        if file.as_os_str().is_empty() {
+            return String::new();
+        }
+        let Some(span) = span else {
+            return format!("{}{} {}\n", gutter, self.paint(BLUE, "-->"), file.display());
+        };
+        let location = format!("{}{} {}:{}\n", gutter, self.paint(BLUE, "-->"), file.display(), span);
+        // Decoded the way sources are, so frames show files that are not UTF-8 too
+        let source = fs::read(file).ok().map(|bytes| decode(&bytes, self.encoding).text);
+        let Some(line) = source.as_deref().and_then(|s| s.lines().nth(span.line - 1)) else {
+            return location;
+        };
+
+        let number = span.line.to_string();
+        let bar = self.paint(BLUE, "|");
+        format!(
+            "{}{} {}\n{} {} {}\n{} {} {}\n",
+            location,
+            gutter,
+            bar,
+            self.paint(BLUE, &number),
+            bar,
+            line,
+            gutter,
+            bar,
+            self.paint(if warning { YELLOW } else { RED }, &caret(line, span.column)),
+        )
+    }
+}
+
+/// Underlines the word starting at a 1-based column, keeping tabs so the caret lines up
+fn caret(line: &str, column: usize) -> String {
+    let mut underline: String = line
+        .chars()
+        .take(column.saturating_sub(1))
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    let word = line
+        .chars()
+        .skip(column.saturating_sub(1))
+        .take_while(|c| !c.is_whitespace())
+        .collect::<String>();
+    let width = word.trim_end_matches([',', ']']).chars().count().max(1);
+    underline.push_str(&"^".repeat(width));
+    underline
+}
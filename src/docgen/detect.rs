@@ -0,0 +1,68 @@
+/*filesummary
+Walks a project, counts its files by language and works out which presets and ignored directories
+its config should have.
+endsummary*/
+
+use super::presets::{self, Preset};
+use super::{DocGenConfig, WalkFilter};
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Build output, dependency and tooling directories that are never worth documenting
+pub const KNOWN_IGNORED_DIRS: &[&str] = &[
+    ".git",
+    "target",
+    "node_modules",
+    "venv",
+    ".venv",
+    "__pycache__",
+    ".tox",
+    ".mypy_cache",
+    "dist",
+    "build",
+    "vendor",
+];
+
+/// What `detect` found in a project
+#[derive(Debug)]
+pub struct Detection {
+    /// Presets that matched at least one file, most common first
+    pub languages: Vec<(&'static Preset, usize)>,
+    /// Entries of `KNOWN_IGNORED_DIRS` that actually exist somewhere in the project
+    pub ignored_dirs: Vec<&'static str>,
+}
+
+/// Scans `dir` and histograms its files by the preset that would document them
+///
+/// Known build and vendor directories are not descended into, so scanning a project with a
+/// large `node_modules` or `target` stays quick.
+pub fn detect(dir: &Path) -> io::Result<Detection> {
+    let ign: Vec<PathBuf> = KNOWN_IGNORED_DIRS.iter().map(PathBuf::from).collect();
+    let mut filter = WalkFilter::new(dir, &ign);
+    filter.respect_ignore_files = true;
+    let mut skipped = Vec::new();
+    let files = DocGenConfig::walk(dir.into(), &filter, &mut skipped)?;
+
+    let mut counts: HashMap<&'static str, (&'static Preset, usize)> = HashMap::new();
+    for file in &files {
+        if let Some(preset) = presets::preset_for_file(file) {
+            counts.entry(preset.name).or_insert((preset, 0)).1 += 1;
+        }
+    }
+
+    let mut languages: Vec<(&'static Preset, usize)> = counts.into_values().collect();
+    // Ties are broken by name so the generated config is stable between runs
+    languages.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.name.cmp(b.name)));
+
+    let ignored_dirs = KNOWN_IGNORED_DIRS
+        .iter()
+        .copied()
+        .filter(|name| skipped.iter().any(|s| s.file_name().is_some_and(|f| f == *name)))
+        .collect();
+
+    Ok(Detection {
+        languages,
+        ignored_dirs,
+    })
+}
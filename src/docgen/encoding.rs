@@ -0,0 +1,56 @@
+/*filesummary
+Turns the bytes of a source file into text, so a Latin-1 or UTF-16 file in a legacy tree is documented instead of failing the run.
+A byte order mark decides the encoding when there is one. Otherwise the file is read as UTF-16 if it looks like it, then as UTF-8, then in the configured `encoding`, and as a last resort with the invalid bytes replaced.
+endsummary*/
+
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+
+/// The text of a source file, and how it was read
+#[derive(Debug)]
+pub struct Decoded {
+    /// The text, with `\r\n` line endings turned into `\n`
+    pub text: String,
+    pub encoding: &'static Encoding,
+    /// Whether some bytes were invalid in that encoding and replaced with U+FFFD
+    pub lossy: bool,
+}
+
+/// Decodes a source file, using `fallback` for files that are not valid UTF-8
+///
+/// Without a fallback, invalid bytes are replaced, which is reported by setting `lossy`.
+pub fn decode(bytes: &[u8], fallback: Option<&'static Encoding>) -> Decoded {
+    let (encoding, body) = match Encoding::for_bom(bytes) {
+        Some((encoding, bom_length)) => (encoding, &bytes[bom_length..]),
+        None => match utf16_without_bom(bytes) {
+            Some(encoding) => (encoding, bytes),
+            None if std::str::from_utf8(bytes).is_ok() => (UTF_8, bytes),
+            None => (fallback.unwrap_or(UTF_8), bytes),
+        },
+    };
+    let (text, lossy) = encoding.decode_without_bom_handling(body);
+    let text = if text.contains('\r') {
+        text.replace("\r\n", "\n")
+    } else {
+        text.into_owned()
+    };
+    Decoded { text, encoding, lossy }
+}
+
+/// Recognises UTF-16 without a byte order mark by the zero bytes of its ASCII characters, which
+/// are at odd positions in little-endian and even ones in big-endian
+fn utf16_without_bom(bytes: &[u8]) -> Option<&'static Encoding> {
+    let sample = &bytes[..bytes.len().min(1024) & !1];
+    if sample.len() < 4 {
+        return None;
+    }
+    let pairs = sample.len() / 2;
+    let even = sample.iter().step_by(2).filter(|b| **b == 0).count();
+    let odd = sample.iter().skip(1).step_by(2).filter(|b| **b == 0).count();
+    if even == 0 && odd * 2 > pairs {
+        Some(UTF_16LE)
+    } else if odd == 0 && even * 2 > pairs {
+        Some(UTF_16BE)
+    } else {
+        None
+    }
+}
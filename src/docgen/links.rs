@@ -0,0 +1,285 @@
+/*filesummary
+Links between sections, written as the title in double square brackets for a section in the same file, or as the file's path, `#` and the title for one in another file.
+They can only be resolved once every file is parsed, which is also when each section learns which sections refer to it.
+endsummary*/
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+
+use pulldown_cmark::{CowStr, Event, LinkType, Tag, TagEnd};
+
+use super::parser::Summary;
+use crate::errors::Span;
+
+/// Where a section, or the top of a page, is shown
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(super) struct Anchor {
+    /// The file name of the page
+    pub(super) page: String,
+    /// The `id` of the section's heading, or empty for the top of the page
+    pub(super) id: String,
+}
+
+impl Anchor {
+    /// A link to the anchor from the given page
+    pub(super) fn href(&self, from_page: &str) -> String {
+        match (self.page == from_page, self.id.is_empty()) {
+            (true, true) => "#".into(),
+            (true, false) => format!("#{}", self.id),
+            (false, true) => self.page.clone(),
+            (false, false) => format!("{}#{}", self.page, self.id),
+        }
+    }
+}
+
+/// A section or file that refers to a section
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Backlink {
+    pub(super) from: Anchor,
+    pub(super) label: String,
+}
+
+/// A reference that matches no section
+#[derive(Debug)]
+pub(super) struct Unresolved {
+    /// The section whose summary has the reference, or `None` for the file summary
+    pub(super) section: Option<usize>,
+    pub(super) target: String,
+    pub(super) span: Span,
+    /// A reference that would work, when the title is in exactly one other file
+    pub(super) suggestion: Option<String>,
+}
+
+/// The `id` of each section's heading, made from its title and unique within the page
+pub(super) fn anchors<'t>(titles: impl Iterator<Item = &'t str>) -> Vec<String> {
+    let mut used = HashSet::new();
+    titles
+        .map(|title| {
+            let mut slug = String::new();
+            for c in title.trim().chars().flat_map(char::to_lowercase) {
+                if c.is_alphanumeric() {
+                    slug.push(c);
+                } else if (c.is_whitespace() || c == '-' || c == '_') && !slug.is_empty() && !slug.ends_with('-') {
+                    slug.push('-');
+                }
+            }
+            let slug = match slug.trim_end_matches('-') {
+                "" => "section".to_string(),
+                slug => slug.to_string(),
+            };
+            let mut id = slug.clone();
+            let mut n = 1;
+            while !used.insert(id.clone()) {
+                n += 1;
+                id = format!("{}-{}", slug, n);
+            }
+            id
+        })
+        .collect()
+}
+
+/// Every section in the project, by file and title
+#[derive(Debug, Default)]
+pub(super) struct Index {
+    /// Keyed by the path of the file relative to the project root, with `/` separators
+    files: HashMap<String, FileSections>,
+}
+
+#[derive(Debug)]
+struct FileSections {
+    page: String,
+    /// The first section with each title, since later ones can't be told apart
+    ids: HashMap<String, String>,
+    /// Titles of the sections left out at the chosen visibility
+    hidden: HashSet<String>,
+}
+
+impl Index {
+    /// Adds a file's sections, given its name, its page, the title and anchor of each section and
+    /// the titles of the sections hidden at the chosen visibility
+    pub(super) fn add<'t>(
+        &mut self,
+        name: &str,
+        page: &str,
+        sections: impl Iterator<Item = (&'t str, &'t str)>,
+        hidden: &[&str],
+    ) {
+        let mut ids = HashMap::new();
+        for (title, id) in sections {
+            ids.entry(title.to_string()).or_insert_with(|| id.to_string());
+        }
+        let hidden = hidden.iter().map(|title| title.to_string()).collect();
+        self.files.insert(name.into(), FileSections { page: page.into(), ids, hidden });
+    }
+
+    /// Whether a reference in the file `from` points at a section hidden at the chosen visibility
+    pub(super) fn is_hidden(&self, from: &str, target: &str) -> bool {
+        let (file, title) = split_target(from, target);
+        self.files.get(&file).is_some_and(|sections| !sections.ids.contains_key(title) && sections.hidden.contains(title))
+    }
+
+    /// Finds the section a reference in the file `from` points at
+    ///
+    /// When there is none, returns a reference that would work if there is exactly one.
+    pub(super) fn resolve(&self, from: &str, target: &str) -> Result<Anchor, Option<String>> {
+        let (file, title) = split_target(from, target);
+        let found = self.files.get(&file).and_then(|sections| {
+            let id = sections.ids.get(title)?;
+            Some(Anchor { page: sections.page.clone(), id: id.clone() })
+        });
+        found.ok_or_else(|| {
+            let mut matches: Vec<&String> = self
+                .files
+                .iter()
+                .filter(|(name, sections)| **name != file && sections.ids.contains_key(title))
+                .map(|(name, _)| name)
+                .collect();
+            matches.sort();
+            match matches.as_slice() {
+                [name] => Some(format!("{}#{}", name, title)),
+                _ => None,
+            }
+        })
+    }
+}
+
+/// Splits a reference into the file it points into and the title it names
+fn split_target<'t>(from: &str, target: &'t str) -> (String, &'t str) {
+    match target.rsplit_once('#') {
+        Some((file, title)) if !file.trim().is_empty() => {
+            let file = file.trim().replace('\\', "/");
+            (file.strip_prefix("./").unwrap_or(&file).to_string(), title.trim())
+        }
+        Some((_, title)) => (from.to_string(), title.trim()),
+        None => (from.to_string(), target.trim()),
+    }
+}
+
+/// The text shown for a reference without its own, which is the title it names
+fn label(target: &str) -> &str {
+    target.rsplit_once('#').map_or(target, |(_, title)| title).trim()
+}
+
+/// What a reference turns into on the page
+enum Target {
+    /// A link to the section
+    Link(String),
+    /// Plain text, since the section is left out at the chosen visibility
+    Hidden,
+    /// Marked text, since no section matches
+    Unresolved,
+}
+
+/// Resolves the references on one page as its summaries are rendered, remembering each one
+pub(super) struct Links<'i> {
+    index: &'i Index,
+    /// The file being rendered, as it is named in references
+    file: &'i str,
+    page: &'i str,
+    found: RefCell<Vec<String>>,
+}
+
+impl<'i> Links<'i> {
+    pub(super) fn new(index: &'i Index, file: &'i str, page: &'i str) -> Self {
+        Links { index, file, page, found: RefCell::new(Vec::new()) }
+    }
+
+    /// Where a reference leads, remembering it unless it points at a hidden section
+    fn target(&self, target: &str) -> Target {
+        if self.index.is_hidden(self.file, target) {
+            return Target::Hidden;
+        }
+        self.found.borrow_mut().push(target.to_string());
+        match self.index.resolve(self.file, target) {
+            Ok(anchor) => Target::Link(anchor.href(self.page)),
+            Err(_) => Target::Unresolved,
+        }
+    }
+
+    /// The references met since the last call, in order
+    pub(super) fn take(&self) -> Vec<String> {
+        self.found.take()
+    }
+
+    /// Turns the `[[...]]` links parsed from a summary into links to their sections
+    ///
+    /// Unresolved references are kept as text, marked so they stand out, and references to hidden
+    /// sections are kept as plain text.
+    pub(super) fn markdown<'e>(&self, events: impl Iterator<Item = Event<'e>>) -> Vec<Event<'e>> {
+        let mut out = Vec::new();
+        // Inside a reference: the event closing it, and whether its text is replaced by the title
+        let mut open: Option<(Option<Event<'e>>, bool)> = None;
+        for event in events {
+            match event {
+                Event::Start(Tag::Link { link_type: LinkType::WikiLink { has_pothole }, dest_url, title, id }) => {
+                    let close = match self.target(&dest_url) {
+                        Target::Link(href) => {
+                            out.push(Event::Start(Tag::Link {
+                                link_type: LinkType::WikiLink { has_pothole },
+                                dest_url: CowStr::from(href),
+                                title,
+                                id,
+                            }));
+                            Some(Event::End(TagEnd::Link))
+                        }
+                        Target::Hidden => None,
+                        Target::Unresolved => {
+                            out.push(Event::Html("<span class=\"unresolved\">".into()));
+                            Some(Event::Html("</span>".into()))
+                        }
+                    };
+                    if !has_pothole {
+                        out.push(Event::Text(CowStr::from(label(&dest_url).to_string())));
+                    }
+                    open = Some((close, !has_pothole));
+                }
+                Event::End(TagEnd::Link) if open.is_some() => {
+                    if let Some((Some(close), _)) = open.take() {
+                        out.push(close);
+                    }
+                }
+                Event::Text(_) | Event::Code(_) if open.as_ref().is_some_and(|(_, replaced)| *replaced) => {}
+                event => out.push(event),
+            }
+        }
+        out
+    }
+
+    /// Turns `[[...]]` links in plain text into HTML links, passing the rest through `text`
+    pub(super) fn plain(&self, content: &str, text: impl Fn(&str) -> String) -> String {
+        let mut html = String::new();
+        let mut rest = content;
+        while let Some((before, after)) = rest.split_once("[[") {
+            let Some((reference, after)) = after.split_once("]]") else { break };
+            html.push_str(&text(before));
+            let (target, shown) = match reference.split_once('|') {
+                Some((target, shown)) => (target, shown.trim()),
+                None => (reference, label(reference)),
+            };
+            match self.target(target.trim()) {
+                Target::Link(href) => html.push_str(&format!("<a href=\"{}\">{}</a>", href, text(shown))),
+                Target::Hidden => html.push_str(&text(shown)),
+                Target::Unresolved => html.push_str(&format!("<span class=\"unresolved\">{}</span>", text(shown))),
+            }
+            rest = after;
+        }
+        html.push_str(&text(rest));
+        html
+    }
+}
+
+/// Where a reference is written in a summary, found by looking for it in the summary's lines
+///
+/// The lines are slices of `content`, so their position in it gives the line and column.
+pub(super) fn reference_span(content: &str, summary: &Summary, target: &str) -> Span {
+    let needle = format!("[[{}", target);
+    let lines = summary.lines.iter().chain(summary.tags.iter().flat_map(|t| &t.lines));
+    for line in lines {
+        let Some(column) = line.find(&needle) else { continue };
+        let Some(start) = (line.as_ptr() as usize).checked_sub(content.as_ptr() as usize) else { continue };
+        if start + line.len() <= content.len() {
+            return Span::from_offset(content, start + column);
+        }
+    }
+    summary.span
+}
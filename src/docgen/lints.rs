@@ -0,0 +1,363 @@
+/*filesummary
+Checks for annotations that parse fine but make for poor documentation, such as empty summaries or duplicate titles.
+Each rule is set to `allow`, `warn` or `deny` in the `[lints]` table, and can be turned off in place with `vexdoc-allow(rule)`.
+endsummary*/
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use super::links::Unresolved;
+use super::parser::{ParsedFile, Section, Summary};
+use super::tags::KNOWN_TAGS;
+use crate::codes::ErrorCode;
+use crate::errors::{Span, SubcommandError, UserErrorKind};
+
+/// What happens when a lint rule finds something
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    /// The rule is off
+    Allow,
+    /// Reported, but documentation is still generated
+    Warn,
+    /// Reported as an error, failing the run
+    Deny,
+}
+
+/// The lint rules and their levels, set in a `[lints]` table
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Lints {
+    /// A summary with no text in it
+    empty_summary: Level,
+    /// A section with no code between its title and its end marker
+    empty_code: Level,
+    /// Two sections with the same title in one file
+    duplicate_title: Level,
+    /// A title longer than `max_title_length` characters
+    long_title: Level,
+    max_title_length: usize,
+    /// A summary that does not end with `.`, `!`, `?` or `:`
+    summary_punctuation: Level,
+    /// A file with a file summary but no sections
+    no_sections: Level,
+    /// A section with more than `max_code_lines` lines of code
+    long_code: Level,
+    max_code_lines: usize,
+    /// A summary tag other than the ones VexDoc renders, such as a misspelt `@parm`
+    unknown_tag: Level,
+    /// A `[[Title]]` reference that matches no section
+    unresolved_reference: Level,
+}
+
+impl Default for Lints {
+    fn default() -> Self {
+        Lints {
+            empty_summary: Level::Warn,
+            empty_code: Level::Warn,
+            duplicate_title: Level::Warn,
+            long_title: Level::Warn,
+            max_title_length: 80,
+            summary_punctuation: Level::Allow,
+            no_sections: Level::Warn,
+            long_code: Level::Allow,
+            max_code_lines: 100,
+            unknown_tag: Level::Warn,
+            unresolved_reference: Level::Warn,
+        }
+    }
+}
+
+/// A lint rule, named as in the `[lints]` table and in `vexdoc-allow(rule)`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Rule {
+    EmptySummary,
+    EmptyCode,
+    DuplicateTitle,
+    LongTitle,
+    SummaryPunctuation,
+    NoSections,
+    LongCode,
+    UnknownTag,
+    UnresolvedReference,
+}
+
+impl Rule {
+    const ALL: [Rule; 9] = [
+        Rule::EmptySummary,
+        Rule::EmptyCode,
+        Rule::DuplicateTitle,
+        Rule::LongTitle,
+        Rule::SummaryPunctuation,
+        Rule::NoSections,
+        Rule::LongCode,
+        Rule::UnknownTag,
+        Rule::UnresolvedReference,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Rule::EmptySummary => "empty_summary",
+            Rule::EmptyCode => "empty_code",
+            Rule::DuplicateTitle => "duplicate_title",
+            Rule::LongTitle => "long_title",
+            Rule::SummaryPunctuation => "summary_punctuation",
+            Rule::NoSections => "no_sections",
+            Rule::LongCode => "long_code",
+            Rule::UnknownTag => "unknown_tag",
+            Rule::UnresolvedReference => "unresolved_reference",
+        }
+    }
+
+    fn code(self) -> ErrorCode {
+        match self {
+            Rule::EmptySummary => ErrorCode::EmptySummary,
+            Rule::EmptyCode => ErrorCode::EmptyCode,
+            Rule::DuplicateTitle => ErrorCode::DuplicateTitle,
+            Rule::LongTitle => ErrorCode::LongTitle,
+            Rule::SummaryPunctuation => ErrorCode::SummaryPunctuation,
+            Rule::NoSections => ErrorCode::NoSections,
+            Rule::LongCode => ErrorCode::LongCode,
+            Rule::UnknownTag => ErrorCode::UnknownTag,
+            Rule::UnresolvedReference => ErrorCode::UnresolvedReference,
+        }
+    }
+}
+
+impl Lints {
+    fn level(&self, rule: Rule) -> Level {
+        match rule {
+            Rule::EmptySummary => self.empty_summary,
+            Rule::EmptyCode => self.empty_code,
+            Rule::DuplicateTitle => self.duplicate_title,
+            Rule::LongTitle => self.long_title,
+            Rule::SummaryPunctuation => self.summary_punctuation,
+            Rule::NoSections => self.no_sections,
+            Rule::LongCode => self.long_code,
+            Rule::UnknownTag => self.unknown_tag,
+            Rule::UnresolvedReference => self.unresolved_reference,
+        }
+    }
+}
+
+/// Collects the findings of every enabled rule in one file
+struct Linter<'l> {
+    lints: &'l Lints,
+    path: &'l Path,
+    /// Rules allowed for the whole file
+    allowed: &'l [&'l str],
+    findings: Vec<SubcommandError>,
+}
+
+impl Linter<'_> {
+    fn report(&mut self, rule: Rule, allowed: &[&str], span: Span, causes: String, help: &str) {
+        let level = self.lints.level(rule);
+        if level == Level::Allow || self.allowed.contains(&rule.name()) || allowed.contains(&rule.name()) {
+            return;
+        }
+        self.findings.push(SubcommandError::UserError {
+            code: rule.code(),
+            causes,
+            source: None,
+            kind: match level {
+                Level::Deny => UserErrorKind::Annotations,
+                _ => UserErrorKind::Warning,
+            },
+            file: self.path.into(),
+            span: Some(span),
+            help: Some(format!(
+                "{}, or add `vexdoc-allow({})` to the block",
+                help,
+                rule.name()
+            )),
+        });
+    }
+
+    fn summary(&mut self, summary: &Summary, allowed: &[&str], what: &str) {
+        for tag in summary.tags.iter().filter(|t| !KNOWN_TAGS.contains(&t.name)) {
+            self.report(
+                Rule::UnknownTag,
+                allowed,
+                tag.span,
+                format!("unknown tag `@{}` in {}", tag.name, what),
+                &format!("use one of {}", KNOWN_TAGS.iter().map(|t| format!("@{}", t)).collect::<Vec<_>>().join(", ")),
+            );
+        }
+        let last = summary.lines.iter().rev().map(|l| l.trim()).find(|l| !l.is_empty());
+        match last {
+            // A summary made only of tags, such as a lone `@deprecated`, is not empty
+            None if !summary.tags.is_empty() => {}
+            None => self.report(
+                Rule::EmptySummary,
+                allowed,
+                summary.span,
+                format!("{} is empty", what),
+                "write a sentence or two, or remove the summary markers",
+            ),
+            Some(last) if !ends_with_punctuation(last) => self.report(
+                Rule::SummaryPunctuation,
+                allowed,
+                summary.span,
+                format!("{} does not end with punctuation", what),
+                "end the summary with `.`, `!`, `?` or `:`",
+            ),
+            Some(_) => {}
+        }
+    }
+
+    fn section(&mut self, section: &Section) {
+        let allowed = section.allowed.as_slice();
+        let length = section.title.chars().count();
+        if length > self.lints.max_title_length {
+            self.report(
+                Rule::LongTitle,
+                allowed,
+                section.span,
+                format!(
+                    "the title of section '{}' is {} characters long, more than the limit of {}",
+                    section.title, length, self.lints.max_title_length
+                ),
+                "move the details into the summary",
+            );
+        }
+        if let Some(summary) = &section.summary {
+            self.summary(summary, allowed, &format!("the summary of section '{}'", section.title));
+        }
+        let Some(code) = &section.code else { return };
+        if code.iter().all(|l| l.trim().is_empty()) {
+            self.report(
+                Rule::EmptyCode,
+                allowed,
+                section.span,
+                format!("section '{}' has no code", section.title),
+                "move the end marker below the code it documents",
+            );
+        } else if code.len() > self.lints.max_code_lines {
+            self.report(
+                Rule::LongCode,
+                allowed,
+                section.span,
+                format!(
+                    "section '{}' has {} lines of code, more than the limit of {}",
+                    section.title,
+                    code.len(),
+                    self.lints.max_code_lines
+                ),
+                "split the section into smaller ones",
+            );
+        }
+    }
+}
+
+/// Runs every enabled rule over a parsed file
+///
+/// Warnings have the `Warning` kind; findings of denied rules are annotation errors.
+pub(super) fn lint(path: &Path, parsed: &ParsedFile, lints: &Lints) -> Vec<SubcommandError> {
+    let mut linter = Linter {
+        lints,
+        path,
+        allowed: &parsed.allowed,
+        findings: Vec::new(),
+    };
+    for summary in &parsed.file_summaries {
+        linter.summary(summary, &[], "the file summary");
+    }
+    if let (Some(summary), true) = (parsed.file_summaries.first(), parsed.sections.is_empty()) {
+        linter.report(
+            Rule::NoSections,
+            &[],
+            summary.span,
+            "the file has a file summary but no sections".into(),
+            "add a section, or remove the file summary",
+        );
+    }
+    // A misspelt rule would otherwise allow nothing without a word
+    for &(name, span) in &parsed.allow_names {
+        if Rule::ALL.iter().any(|rule| rule.name() == name) {
+            continue;
+        }
+        let spelled = name.to_lowercase().replace('-', "_");
+        let help = match Rule::ALL.iter().find(|rule| rule.name() == spelled) {
+            Some(rule) => format!("did you mean `{}`?", rule.name()),
+            None => {
+                let names: Vec<&str> = Rule::ALL.iter().map(|rule| rule.name()).collect();
+                format!("the rules are {}", names.join(", "))
+            }
+        };
+        linter.findings.push(SubcommandError::UserError {
+            code: ErrorCode::UnknownLint,
+            causes: format!("`vexdoc-allow` names an unknown rule '{}'", name),
+            source: None,
+            kind: UserErrorKind::Warning,
+            file: path.into(),
+            span: Some(span),
+            help: Some(help),
+        });
+    }
+    let mut titles = HashSet::new();
+    for section in &parsed.sections {
+        if !titles.insert(section.title) {
+            linter.report(
+                Rule::DuplicateTitle,
+                &section.allowed,
+                section.span,
+                format!("section '{}' has the same title as an earlier section", section.title),
+                "give each section in a file its own title",
+            );
+        }
+        linter.section(section);
+    }
+    linter.findings
+}
+
+/// Reports references that match no section, which are only known once every file is parsed
+pub(super) fn unresolved(path: &Path, parsed: &ParsedFile, references: &[Unresolved], lints: &Lints) -> Vec<SubcommandError> {
+    let mut linter = Linter {
+        lints,
+        path,
+        allowed: &parsed.allowed,
+        findings: Vec::new(),
+    };
+    for reference in references {
+        let (allowed, what) = match reference.section.and_then(|i| parsed.sections.get(i)) {
+            Some(section) => (section.allowed.as_slice(), format!("the summary of section '{}'", section.title)),
+            None => (&[][..], "the file summary".to_string()),
+        };
+        let help = match &reference.suggestion {
+            Some(suggestion) => format!("write `[[{}]]` for the section in that file", suggestion),
+            None => "check the title, and write `[[path/to/file#Title]]` for a section in another file".into(),
+        };
+        linter.report(
+            Rule::UnresolvedReference,
+            allowed,
+            reference.span,
+            format!("reference `[[{}]]` in {} does not match any section", reference.target, what),
+            &help,
+        );
+    }
+    linter.findings
+}
+
+/// Whether a summary ends a sentence, looking past closing quotes, brackets and backticks
+fn ends_with_punctuation(text: &str) -> bool {
+    text.trim_end_matches(['"', '\'', ')', ']', '`', '*', '_'])
+        .ends_with(['.', '!', '?', ':'])
+}
+
+/// Reads a `vexdoc-allow(rule, ...)` directive, with or without the comment around it
+///
+/// The directive must be alone on its line, so it is never mistaken for code.
+pub(super) fn allow_directive<'l>(line: &'l str, delimiters: &[&str]) -> Option<Vec<&'l str>> {
+    let mut line = line.trim();
+    // Longer delimiters first, so `--[[` is not read as `--` followed by `[[`
+    let mut delimiters: Vec<&str> = delimiters.iter().copied().filter(|d| !d.is_empty()).collect();
+    delimiters.sort_by_key(|d| std::cmp::Reverse(d.len()));
+    for delimiter in delimiters {
+        line = line.strip_prefix(delimiter).unwrap_or(line).trim_start();
+        line = line.strip_suffix(delimiter).unwrap_or(line).trim_end();
+    }
+    let rules = line.strip_prefix("vexdoc-allow(")?.strip_suffix(')')?;
+    Some(rules.split(',').map(str::trim).filter(|r| !r.is_empty()).collect())
+}
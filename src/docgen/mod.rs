@@ -1,171 +1,938 @@
-//! Documentation generation module
-/*startsummary
+/*filesummary
 This module handles the generation of HTML documentation from source files.
 endsummary*/
 
-use crate::errors::{SubcommandError, UserErrorKind, ValidationError};
+use crate::codes::ErrorCode;
+use crate::diagnostics::Renderer;
+use crate::errors::{Span, SubcommandError, UserErrorKind, ValidationError};
+use self::encoding::decode;
+use self::links::{anchors, reference_span, Anchor, Backlink, Index, Links, Unresolved};
+use self::lints::{lint, Lints};
+use self::parser::{parse, CommentSyntax, ParsedFile, Summary};
+use self::presets::Preset;
+use self::tags::render_summary;
 use build_html::{Container, ContainerType, Html, HtmlContainer, HtmlElement, HtmlPage, HtmlTag};
-use serde::Deserialize;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer};
+use toml::de::{DeTable, DeValue};
+use toml::Spanned;
+use encoding_rs::Encoding;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::collections::{BTreeMap, HashMap};
+use std::env;
 use std::ffi::OsString;
 use std::fs::{self, DirBuilder, File};
 use std::io::{self, ErrorKind, Write};
 use std::ops::Deref;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use rayon::prelude::*;
 use indicatif::{ProgressBar, ProgressStyle};
 
+pub mod detect;
+pub mod encoding;
+mod links;
+pub mod lints;
+mod parser;
+pub mod presets;
+mod signature;
+mod tags;
+
 #[cfg(test)]
 mod tests;
 
 /// Configuration for VexDoc processing
 #[derive(Debug, Deserialize)]
 pub struct DocGenConfig {
+    #[serde(default)]
     inline_comments: String,
-    multi_comments: Vec<String>,
+    #[serde(default, deserialize_with = "deserialize_delimiter_pairs")]
+    multi_comments: Vec<DelimiterPair>,
+    /// Whether block comments nest, as in Rust, Haskell and OCaml
+    #[serde(default)]
+    nested_comments: bool,
+    /// String literals that can span lines, so markers inside them are read as code
+    #[serde(default, deserialize_with = "deserialize_delimiter_pairs")]
+    multiline_strings: Vec<DelimiterPair>,
     ignored_dirs: Vec<PathBuf>,
     file_extensions: Vec<String>,
+    /// Directories to search for files, relative to the project root (defaults to the root)
+    #[serde(default)]
+    source_roots: Vec<PathBuf>,
+    /// Where generated pages go, relative to the project root (defaults to `docs`)
+    #[serde(default)]
+    output_dir: Option<PathBuf>,
+    /// Gitignore-style globs a file must match to be documented, relative to the project root
+    #[serde(default)]
+    include: Vec<String>,
+    /// Gitignore-style globs for files and directories to skip, relative to the project root
+    #[serde(default)]
+    exclude: Vec<String>,
+    /// Whether `.gitignore`, `.ignore` and `.vexdocignore` files are honoured during discovery
+    #[serde(default = "default_respect_gitignore")]
+    respect_gitignore: bool,
+    #[serde(skip)]
+    include_matcher: Option<Gitignore>,
+    #[serde(skip)]
+    exclude_matcher: Option<Gitignore>,
+    /// Whether a block still open at the end of a file is written out instead of being an error
+    #[serde(default)]
+    allow_unterminated: bool,
+    /// Where the code of a section ends, when not at an `ENDVEXDOC` line
+    #[serde(default)]
+    block_end: BlockEnd,
+    /// The encoding of source files that are not valid UTF-8, as a WHATWG label like `windows-1252`
+    #[serde(default)]
+    encoding: Option<String>,
+    #[serde(skip)]
+    fallback_encoding: Option<&'static Encoding>,
+    /// Which sections are written, set with `--visibility`
+    #[serde(skip)]
+    visibility: Visibility,
+    /// How summaries are rendered: as CommonMark, or as plain text joined into one paragraph
+    #[serde(default)]
+    summary_format: SummaryFormat,
+    #[serde(default)]
+    markers: Markers,
+    #[serde(default)]
+    lints: Lints,
+    #[serde(default)]
+    languages: BTreeMap<String, LanguageProfile>,
+    /// Directory holding the config file; discovery and output are relative to it
+    #[serde(skip)]
+    root: PathBuf,
+    /// The config file itself, for error messages
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+fn default_respect_gitignore() -> bool {
+    true
+}
+
+/// How the text of summaries is turned into HTML
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SummaryFormat {
+    /// CommonMark, so summaries can have paragraphs, lists, links, inline code and examples
+    Markdown,
+    /// Every line joined with spaces into one paragraph, as VexDoc always did
+    #[default]
+    Plain,
+}
+
+/// Which sections `vexdoc generate` writes, by their `[internal]` and `[public]` tags
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Visibility {
+    /// Only public sections, for documentation published outside the team
+    #[default]
+    Public,
+    /// Only internal sections
+    Internal,
+    /// Every section, marking the internal ones
+    All,
+}
+
+impl Visibility {
+    /// Whether a section, internal or not, is written
+    fn shows(self, internal: bool) -> bool {
+        match self {
+            Visibility::Public => !internal,
+            Visibility::Internal => internal,
+            Visibility::All => true,
+        }
+    }
+}
+
+impl FromStr for Visibility {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "public" => Ok(Visibility::Public),
+            "internal" => Ok(Visibility::Internal),
+            "all" => Ok(Visibility::All),
+            _ => Err(format!("unknown visibility '{}', expected one of public, internal, all", s)),
+        }
+    }
+}
+
+/// How the parser finds the end of a section's code
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BlockEnd {
+    /// Only at an `ENDVEXDOC` line, so a forgotten one takes in the rest of the file
+    #[default]
+    Explicit,
+    /// At the next title marker or the end of the file
+    NextTitle,
+    /// When a line is indented no deeper than the first line of the code, as in Python
+    Indent,
+    /// When the braces opened in the code are closed again, as in C, Rust and Java
+    Braces,
+}
+
+/// Comment syntax for one language, declared as a `[languages.<name>]` table
+///
+/// When `extensions` is left out, the table name is used as the extension,
+/// so `[languages.py]` applies to `.py` files.
+#[derive(Debug, Deserialize)]
+pub struct LanguageProfile {
+    #[serde(default)]
+    extensions: Vec<String>,
+    /// Whole file names, for files such as `Makefile` that have no extension to go by
+    #[serde(default)]
+    filenames: Vec<String>,
+    #[serde(default)]
+    inline_comments: String,
+    #[serde(default, deserialize_with = "deserialize_delimiter_pairs")]
+    multi_comments: Vec<DelimiterPair>,
+    #[serde(default)]
+    nested_comments: bool,
+    #[serde(default, deserialize_with = "deserialize_delimiter_pairs")]
+    multiline_strings: Vec<DelimiterPair>,
+    /// Overrides the top-level `[markers]` for this language
+    markers: Option<Markers>,
+    /// Overrides the top-level `block_end` for this language
+    block_end: Option<BlockEnd>,
+}
+
+/// An opening and closing block comment delimiter, such as `/*` and `*/`
+#[derive(Debug, Clone, PartialEq)]
+pub struct DelimiterPair {
+    pub open: String,
+    pub close: String,
+}
+
+impl DelimiterPair {
+    pub fn new(open: &str, close: &str) -> Self {
+        DelimiterPair {
+            open: open.into(),
+            close: close.into(),
+        }
+    }
+
+    /// How much a line changes the nesting depth of this kind of comment
+    fn depth_change(&self, line: &str) -> isize {
+        // Delimiters like `"""` open and close alike, so they cannot nest
+        if self.open == self.close {
+            return 0;
+        }
+        line.matches(self.open.as_str()).count() as isize
+            - line.matches(self.close.as_str()).count() as isize
+    }
+}
+
+/// Reads `multi_comments` or `multiline_strings` as either one `["/*", "*/"]` pair or a list of pairs
+fn deserialize_delimiter_pairs<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<DelimiterPair>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Raw {
+        Flat(Vec<String>),
+        Pairs(Vec<Vec<String>>),
+    }
+
+    // A lone opening delimiter is kept with an empty close so validation can explain it
+    let pair = |items: &[String]| match items {
+        [open] => Ok(DelimiterPair::new(open, "")),
+        [open, close] => Ok(DelimiterPair::new(open, close)),
+        _ => Err(D::Error::custom(
+            "each pair must be an opening and a closing delimiter",
+        )),
+    };
+    match Raw::deserialize(deserializer)? {
+        Raw::Flat(flat) => flat.chunks(2).map(pair).collect(),
+        Raw::Pairs(pairs) => pairs.iter().map(|p| pair(p)).collect(),
+    }
+}
+
+/// The keywords that follow a comment delimiter to form VexDoc annotations
+///
+/// Set in a `[markers]` table, e.g. `title = "!!"` when `//!` already means something else.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Markers {
+    /// Follows `inline_comments` to start a titled section
+    title: String,
+    /// Follows the opening block comment delimiter to start a section summary
+    start_summary: String,
+    /// Precedes the closing block comment delimiter to end a summary
+    end_summary: String,
+    /// Follows the opening block comment delimiter to start the file summary
+    file_summary: String,
+    /// Follows `inline_comments` to end a section
+    end: String,
+    /// Skip a `#!/...` interpreter line at the top of a file instead of reading it as a title
+    ignore_shebang: bool,
+}
+
+impl Default for Markers {
+    fn default() -> Self {
+        Markers {
+            title: "!".into(),
+            start_summary: "startsummary".into(),
+            end_summary: "endsummary".into(),
+            file_summary: "filesummary".into(),
+            end: "ENDVEXDOC".into(),
+            ignore_shebang: true,
+        }
+    }
+}
+
+impl Markers {
+    fn validate(&self, profile: Option<&str>, spans: &ConfigSpans, validation_errors: &mut Vec<ValidationError>) {
+        let (table, path) = match profile {
+            Some(name) => (format!("[languages.{}.markers]", name), vec!["languages", name, "markers"]),
+            None => ("[markers]".to_string(), vec!["markers"]),
+        };
+        let keywords = [
+            ("title", &self.title),
+            ("start_summary", &self.start_summary),
+            ("end_summary", &self.end_summary),
+            ("file_summary", &self.file_summary),
+            ("end", &self.end),
+        ];
+        for (key, value) in keywords {
+            if value.trim().is_empty() {
+                validation_errors.push(
+                    ValidationError::new(
+                        ErrorCode::EmptyMarker,
+                        format!("Marker '{}' in {} is empty", key, table),
+                        format!("Set {} to a non-empty keyword or remove it to use the default", key),
+                    )
+                    .with_span(spans.key(&[path.as_slice(), &[key]].concat())),
+                );
+            }
+        }
+    }
+}
+
+/// Locations of the keys and values in a VexDoc.toml, so config errors can point at a line
+struct ConfigSpans<'i> {
+    text: &'i str,
+    table: Option<Spanned<DeTable<'i>>>,
+}
+
+impl<'i> ConfigSpans<'i> {
+    fn new(text: &'i str) -> Self {
+        ConfigSpans { text, table: DeTable::parse(text).ok() }
+    }
+
+    fn lookup(&self, keys: &[&str]) -> Option<&Spanned<DeValue<'i>>> {
+        fn find<'t, 'i>(table: &'t DeTable<'i>, key: &str) -> Option<&'t Spanned<DeValue<'i>>> {
+            table
+                .iter()
+                .find(|(k, _)| k.get_ref().as_ref() == key)
+                .map(|(_, v)| v)
+        }
+        let (first, rest) = keys.split_first()?;
+        let mut value = find(self.table.as_ref()?.get_ref(), first)?;
+        for key in rest {
+            value = find(value.get_ref().as_table()?, key)?;
+        }
+        Some(value)
+    }
+
+    /// The location of the value under a key path such as `["languages", "python", "extensions"]`
+    fn key(&self, keys: &[&str]) -> Option<Span> {
+        self.lookup(keys)
+            .map(|value| Span::from_offset(self.text, value.span().start))
+    }
+
+    /// The location of one element of the array under a key path
+    fn item(&self, keys: &[&str], index: usize) -> Option<Span> {
+        self.lookup(keys)?
+            .get_ref()
+            .get(index)
+            .map(|value| Span::from_offset(self.text, value.span().start))
+    }
+}
+
+struct WalkFilter<'a> {
+    /// Directory that include/exclude patterns are relative to
+    root: &'a Path,
+    ignored_dirs: &'a [PathBuf],
+    include: Option<&'a Gitignore>,
+    exclude: Option<&'a Gitignore>,
+    /// Whether `.gitignore`, `.ignore` and `.vexdocignore` files are honoured
+    respect_ignore_files: bool,
+    /// Where the pages are written, canonicalized, so earlier output is never documented
+    output_dir: Option<PathBuf>,
+}
+
+/// Ignore files read in every directory, lowest precedence first
+const IGNORE_FILES: &[&str] = &[".gitignore", ".ignore", ".vexdocignore"];
+
+impl<'a> WalkFilter<'a> {
+    /// A filter that only skips directories by name
+    fn new(root: &'a Path, ignored_dirs: &'a [PathBuf]) -> Self {
+        WalkFilter {
+            root,
+            ignored_dirs,
+            include: None,
+            exclude: None,
+            respect_ignore_files: false,
+            output_dir: None,
+        }
+    }
+
+    /// Reads the ignore files in `dir` into one matcher, if there are any
+    fn load_ignore_files(&self, dir: &Path) -> Option<Gitignore> {
+        if !self.respect_ignore_files {
+            return None;
+        }
+        let mut builder = GitignoreBuilder::new(dir);
+        let mut found = false;
+        for name in IGNORE_FILES {
+            let file = dir.join(name);
+            if file.is_file() {
+                found = true;
+                // Like git, skip malformed lines rather than failing the whole walk
+                let _ = builder.add(file);
+            }
+        }
+        if found {
+            builder.build().ok()
+        } else {
+            None
+        }
+    }
+
+    /// Whether a directory is the output directory, checking names first to spare most lookups
+    fn is_output_dir(&self, dir: &Path) -> bool {
+        self.output_dir.as_ref().is_some_and(|out| {
+            out.file_name() == dir.file_name() && dir.canonicalize().is_ok_and(|dir| dir == *out)
+        })
+    }
+
+    fn is_excluded(&self, path: &Path, is_dir: bool) -> bool {
+        let relative = path.strip_prefix(self.root).unwrap_or(path);
+        self.exclude
+            .is_some_and(|exclude| exclude.matched(relative, is_dir).is_ignore())
+    }
+
+    fn is_included(&self, path: &Path) -> bool {
+        let relative = path.strip_prefix(self.root).unwrap_or(path);
+        self.include.is_none_or(|include| {
+            include
+                .matched_path_or_any_parents(relative, false)
+                .is_ignore()
+        })
+    }
 }
 
 impl DocGenConfig {
-    /// Loads configuration from VexDoc.toml
+    /// Loads configuration from the nearest VexDoc.toml in the current directory or its parents
     pub fn read_config() -> Result<DocGenConfig, SubcommandError> {
-        let config =
-            fs::read_to_string("./VexDoc.toml").map_err(|e| SubcommandError::FileReadError(e))?;
+        let current_dir = env::current_dir().map_err(SubcommandError::FileReadError)?;
+        let path = DocGenConfig::find_config(&current_dir).ok_or_else(|| {
+            SubcommandError::FileReadError(io::Error::new(
+                ErrorKind::NotFound,
+                "no VexDoc.toml found in the current directory or any of its parents",
+            ))
+        })?;
+        let mut config = DocGenConfig::read_config_at(&path)?;
+        // Keep paths short when running from the project root itself
+        if config.root == current_dir {
+            config.root = ".".into();
+        }
+        Ok(config)
+    }
+
+    /// Loads configuration from an explicit config file path
+    pub fn read_config_at(path: &Path) -> Result<DocGenConfig, SubcommandError> {
+        let config = fs::read_to_string(path).map_err(SubcommandError::FileReadError)?;
+        let mut config = DocGenConfig::parse_config(&config, path)?;
+        config.root = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.into(),
+            _ => ".".into(),
+        };
+        Ok(config)
+    }
+
+    /// Searches `start` and then each of its ancestors for a VexDoc.toml, like git and cargo do
+    pub fn find_config(start: &Path) -> Option<PathBuf> {
+        start
+            .ancestors()
+            .map(|dir| dir.join("VexDoc.toml"))
+            .find(|candidate| candidate.is_file())
+    }
+
+    /// Parses and validates the contents of a VexDoc.toml
+    fn parse_config(text: &str, path: &Path) -> Result<DocGenConfig, SubcommandError> {
         // Ideally the serde stuff should not fail
-        let config: DocGenConfig =
-            toml::from_str(&config).map_err(|e| SubcommandError::UserError {
-                causes: "fix missing values/incorrect syntax".into(),
+        let mut config: DocGenConfig =
+            toml::from_str(text).map_err(|e| SubcommandError::UserError {
+                code: ErrorCode::ConfigSyntax,
+                causes: e.message().to_string(),
+                span: e.span().map(|range| Span::from_offset(text, range.start)),
+                help: Some("fix missing values or incorrect syntax".into()),
                 source: Some(Box::new(e)),
                 kind: UserErrorKind::Config,
-                file: "./VexDoc.toml".into(),
+                file: path.into(),
             })?;
+        let spans = ConfigSpans::new(text);
+        config.root = ".".into();
+        config.path = path.into();
+
+        for (name, profile) in config.languages.iter_mut() {
+            if profile.extensions.is_empty() {
+                profile.extensions.push(name.clone());
+            }
+        }
 
         let mut validation_errors = Vec::new();
 
-        if config.multi_comments.is_empty() {
-            validation_errors.push(ValidationError::new(
-                "No multiline comment delimiters specified".to_string(),
-                "Add multiline comment delimiters, e.g., multi_comments = [\"/*\", \"*/\"]".to_string(),
-            ));
+        if let Some(label) = &config.encoding {
+            config.fallback_encoding = Encoding::for_label(label.trim().as_bytes());
+            if config.fallback_encoding.is_none() {
+                validation_errors.push(
+                    ValidationError::new(
+                        ErrorCode::UnknownEncoding,
+                        format!("Unknown encoding '{}'", label),
+                        "Use an encoding label such as \"windows-1252\", \"latin1\", \"shift_jis\" or \"utf-16le\"".to_string(),
+                    )
+                    .with_span(spans.key(&["encoding"])),
+                );
+            }
         }
-        if config.inline_comments.is_empty() {
-            validation_errors.push(ValidationError::new(
-                "No inline comment delimiter specified".to_string(),
-                "Add an inline comment delimiter, e.g., inline_comments = \"//\"".to_string(),
-            ));
+
+        config.include_matcher = compile_globs(&config.include, "include", &spans, &mut validation_errors);
+        config.exclude_matcher = compile_globs(&config.exclude, "exclude", &spans, &mut validation_errors);
+
+        // The top-level syntax is only required when some extension has no language profile
+        let needs_default = config.languages.is_empty()
+            || config
+                .file_extensions
+                .iter()
+                .any(|ext| config.profile_for(ext).is_none());
+        if needs_default || !config.inline_comments.is_empty() || !config.multi_comments.is_empty() {
+            validate_syntax(
+                None,
+                &config.inline_comments,
+                &config.multi_comments,
+                &spans,
+                &mut validation_errors,
+            );
         }
-        if config.file_extensions.is_empty() {
-            validation_errors.push(ValidationError::new(
-                "No file extensions specified".to_string(),
-                "Add file extensions without the period, e.g., file_extensions = [\"rs\", \"py\", \"c\"]".to_string(),
-            ));
+        if config.file_extensions.is_empty() && config.languages.is_empty() {
+            validation_errors.push(
+                ValidationError::new(
+                    ErrorCode::NoFileExtensions,
+                    "No file extensions specified".to_string(),
+                    "Add file extensions without the period, e.g., file_extensions = [\"rs\", \"py\", \"c\"]".to_string(),
+                )
+                .with_span(spans.key(&["file_extensions"])),
+            );
         }
-        
-        // Validate multiline comment pairs
-        if config.multi_comments.len() == 1 {
-            validation_errors.push(ValidationError::new(
-                "Multiline comments must have both opening and closing delimiters".to_string(),
-                "Add both opening and closing delimiters, e.g., multi_comments = [\"/*\", \"*/\"]".to_string(),
-            ));
+
+        config.markers.validate(None, &spans, &mut validation_errors);
+        for (name, profile) in &config.languages {
+            validate_syntax(
+                Some(name),
+                &profile.inline_comments,
+                &profile.multi_comments,
+                &spans,
+                &mut validation_errors,
+            );
+            if let Some(markers) = &profile.markers {
+                markers.validate(Some(name), &spans, &mut validation_errors);
+            }
+            let extensions_key = ["languages", name, "extensions"];
+            for (i, ext) in profile.extensions.iter().enumerate() {
+                let claimed_by = config
+                    .languages
+                    .iter()
+                    .find(|(_, other)| other.extensions.contains(ext))
+                    .map(|(other_name, _)| other_name);
+                if claimed_by != Some(name) {
+                    validation_errors.push(
+                        ValidationError::new(
+                            ErrorCode::DuplicateExtension,
+                            format!("Extension '{}' is listed in more than one language profile", ext),
+                            format!("Remove '{}' from either [languages.{}] or [languages.{}]", ext, claimed_by.unwrap_or(name), name),
+                        )
+                        .with_span(spans.item(&extensions_key, i)),
+                    );
+                }
+            }
         }
-        
+
         // Validate file extensions format
-        for ext in &config.file_extensions {
+        let top_level_extensions = config
+            .file_extensions
+            .iter()
+            .enumerate()
+            .map(|(i, ext)| (ext, spans.item(&["file_extensions"], i)));
+        let profile_extensions = config.languages.iter().flat_map(|(name, profile)| {
+            let spans = &spans;
+            profile
+                .extensions
+                .iter()
+                .enumerate()
+                .map(move |(i, ext)| (ext, spans.item(&["languages", name, "extensions"], i)))
+        });
+        for (ext, span) in top_level_extensions.chain(profile_extensions) {
             if ext.starts_with('.') {
                 let error_msg = format!("File extension '{}' should not start with a period", ext);
-                validation_errors.push(ValidationError::new(
-                    error_msg,
-                    "Remove the leading period from file extensions".to_string(),
-                ));
+                validation_errors.push(
+                    ValidationError::new(
+                        ErrorCode::LeadingPeriod,
+                        error_msg,
+                        "Remove the leading period from file extensions".to_string(),
+                    )
+                    .with_span(span),
+                );
             }
         }
 
-        if !validation_errors.is_empty() {
-            let mut error_message = String::new();
-            error_message.push_str("Configuration validation failed:\n\n");
-            
-            for (i, error) in validation_errors.iter().enumerate() {
-                error_message.push_str(&format!("{}. {}\n", i + 1, error.message));
+        // Each problem is its own error, so it can be shown next to the line it is about
+        SubcommandError::from_errors(
+            validation_errors
+                .into_iter()
+                .map(|error| error.into_error(path))
+                .collect(),
+        )?;
+
+        Ok(config)
+    }
+
+    /// Finds the language profile that claims the given extension, if any
+    fn profile_for(&self, ext: &str) -> Option<&LanguageProfile> {
+        self.languages
+            .values()
+            .find(|profile| profile.extensions.iter().any(|e| e == ext))
+    }
+
+    /// Finds the language profile for a file, by its whole name first and then by its extension
+    fn profile_for_file(&self, path: &Path) -> Option<&LanguageProfile> {
+        let name = path.file_name().and_then(|name| name.to_str());
+        self.languages
+            .values()
+            .find(|profile| name.is_some_and(|name| profile.filenames.iter().any(|f| f == name)))
+            .or_else(|| path.extension().and_then(|ext| ext.to_str()).and_then(|ext| self.profile_for(ext)))
+    }
+
+    /// Picks the comment syntax for a file, falling back to the top-level syntax
+    fn syntax_for(&self, path: &Path) -> Option<CommentSyntax<'_>> {
+        match self.profile_for_file(path) {
+            Some(profile) => Some(CommentSyntax {
+                inline_comments: &profile.inline_comments,
+                multi_comments: &profile.multi_comments,
+                nested_comments: profile.nested_comments,
+                multiline_strings: &profile.multiline_strings,
+                markers: profile.markers.as_ref().unwrap_or(&self.markers),
+                block_end: profile.block_end.unwrap_or(self.block_end),
+            }),
+            None if self.inline_comments.is_empty() && self.multi_comments.is_empty() => None,
+            None => Some(CommentSyntax {
+                inline_comments: &self.inline_comments,
+                multi_comments: &self.multi_comments,
+                nested_comments: self.nested_comments,
+                multiline_strings: &self.multiline_strings,
+                markers: &self.markers,
+                block_end: self.block_end,
+            }),
+        }
+    }
+
+    /// Finds the comment syntax for a file, failing when none is configured for its type
+    fn require_syntax(&self, path: &Path) -> Result<CommentSyntax<'_>, SubcommandError> {
+        self.syntax_for(path).ok_or_else(|| SubcommandError::UserError {
+            code: ErrorCode::UnsupportedFileType,
+            causes: "no comment syntax is configured for this file type".into(),
+            source: None,
+            kind: UserErrorKind::Config,
+            file: path.into(),
+            span: None,
+            help: Some("add a [languages.<ext>] table for this file type, or set the top-level inline_comments and multi_comments".into()),
+        })
+    }
+
+    pub fn get_files(&self) -> Result<Vec<PathBuf>, SubcommandError> {
+        let roots = if self.source_roots.is_empty() {
+            vec![self.root.clone()]
+        } else {
+            self.source_roots.iter().map(|r| self.root.join(r)).collect()
+        };
+
+        let mut files = Vec::new();
+        for root in roots {
+            if !root.is_dir() {
+                return Err(SubcommandError::UserError {
+                    code: ErrorCode::MissingSourceRoot,
+                    causes: format!("source root {} does not exist or is not a directory", root.display()),
+                    source: None,
+                    kind: UserErrorKind::Config,
+                    file: self.path.clone(),
+                    span: None,
+                    help: Some("fix or remove it in source_roots".into()),
+                });
             }
-            
-            error_message.push_str("\nSuggested fixes:\n");
-            for (i, error) in validation_errors.iter().enumerate() {
-                error_message.push_str(&format!("{}. {}\n", i + 1, error.suggestion));
+            let filter = WalkFilter {
+                root: &self.root,
+                ignored_dirs: &self.ignored_dirs,
+                include: self.include_matcher.as_ref(),
+                exclude: self.exclude_matcher.as_ref(),
+                respect_ignore_files: self.respect_gitignore,
+                output_dir: self.output_dir().canonicalize().ok(),
+            };
+            let found =
+                DocGenConfig::get_files_helper(root, &filter).map_err(SubcommandError::FileReadError)?;
+            files.extend(found);
+        }
+        // Overlapping roots such as "src" and "src/bin" would otherwise document files twice
+        files.sort();
+        files.dedup();
+
+        // Files covered by a language profile are documented even if their extension is
+        // missing from file_extensions
+        let filtered_files: Vec<PathBuf> = files
+            .into_iter()
+            .filter(|f| {
+                self.profile_for_file(f).is_some()
+                    || f.extension()
+                        .is_some_and(|ext| self.file_extensions.iter().any(|e| OsString::from(e) == ext))
+            })
+            .collect();
+        Ok(filtered_files)
+    }
+
+    /// Directory the generated pages are written to
+    pub fn output_dir(&self) -> PathBuf {
+        match &self.output_dir {
+            Some(dir) => self.root.join(dir),
+            None => self.root.join("docs"),
+        }
+    }
+
+    /// The encoding set with `encoding`, for sources that are neither UTF-8 nor UTF-16
+    pub fn fallback_encoding(&self) -> Option<&'static Encoding> {
+        self.fallback_encoding
+    }
+
+    /// Keeps only the sections shown at the given visibility, as `--visibility` does
+    pub fn set_visibility(&mut self, visibility: Visibility) {
+        self.visibility = visibility;
+    }
+
+    /// Overrides `output_dir`, resolving relative paths against the working directory
+    pub fn set_output_dir(&mut self, dir: PathBuf) -> io::Result<()> {
+        self.output_dir = Some(if dir.is_relative() {
+            env::current_dir()?.join(dir)
+        } else {
+            dir
+        });
+        Ok(())
+    }
+
+    /// The file name of a source file's page, e.g. `src_main-rs.html` for `src/main.rs`
+    fn page_name(&self, path: &Path) -> String {
+        let name = self.relative_path(path).display().to_string();
+        format!("{}.html", name.replace(".", "-").replace("/", "_").replace("\\", "_"))
+    }
+
+    /// Path of a source file relative to the project root, used to name its page
+    fn relative_path(&self, path: &Path) -> PathBuf {
+        if let Ok(relative) = path.strip_prefix(&self.root) {
+            return relative.into();
+        }
+        // Paths passed with --files are relative to the working directory, not the root
+        if let (Ok(path), Ok(root)) = (path.canonicalize(), self.root.canonicalize()) {
+            if let Ok(relative) = path.strip_prefix(root) {
+                return relative.into();
             }
-            
-            return Err(SubcommandError::UserError {
-                causes: error_message,
-                source: None,
-                kind: UserErrorKind::Config,
-                file: "VexDoc.toml".into(),
-            });
         }
+        path.strip_prefix("./").unwrap_or(path).into()
+    }
 
-        Ok(config)
+    fn get_files_helper(path: PathBuf, filter: &WalkFilter) -> io::Result<Vec<PathBuf>> {
+        DocGenConfig::walk(path, filter, &mut Vec::new())
     }
 
-    pub fn get_files(&self) -> Result<Vec<PathBuf>, SubcommandError> {
-        match DocGenConfig::get_files_helper(".".into(), &self.ignored_dirs) {
-            Err(e) => return Err(SubcommandError::FileReadError(e)),
-            Ok(files) => {
-                // Filter files by extension more efficiently
-                let filtered_files: Vec<PathBuf> = files
-                    .into_iter()
-                    .filter(|f| {
-                        f.extension()
-                            .map(|ext| self.file_extensions.iter().any(|e| OsString::from(e) == ext))
-                            .unwrap_or(false)
-                    })
-                    .collect();
-                Ok(filtered_files)
+    /// Recursively lists files under `path`, recording every ignored directory in `skipped`
+    ///
+    /// Excluded directories are never descended into.
+    fn walk(path: PathBuf, filter: &WalkFilter, skipped: &mut Vec<PathBuf>) -> io::Result<Vec<PathBuf>> {
+        // Ignore files between the project root and a source root still apply inside it
+        let mut ignores = Vec::new();
+        if let Ok(relative) = path.strip_prefix(filter.root) {
+            let mut dir = filter.root.to_path_buf();
+            for component in relative.components() {
+                ignores.extend(filter.load_ignore_files(&dir));
+                dir.push(component);
             }
         }
+        // A source root can itself be ignored, e.g. a `venv` root with `venv/` in .gitignore
+        if path != filter.root
+            && (is_ignored(&ignores, &path, true) || filter.is_excluded(&path, true) || filter.is_output_dir(&path))
+        {
+            skipped.push(path);
+            return Ok(Vec::new());
+        }
+        DocGenConfig::walk_dir(path, filter, &mut ignores, skipped)
     }
 
-    fn get_files_helper(path: PathBuf, ign: &[PathBuf]) -> io::Result<Vec<PathBuf>> {
+    /// Lists one directory and recurses into its children
+    ///
+    /// `ignores` holds the ignore files of every directory above `path`, outermost first.
+    fn walk_dir(
+        path: PathBuf,
+        filter: &WalkFilter,
+        ignores: &mut Vec<Gitignore>,
+        skipped: &mut Vec<PathBuf>,
+    ) -> io::Result<Vec<PathBuf>> {
         let mut output = Vec::new();
+        let own_ignores = filter.load_ignore_files(&path);
+        let pushed = own_ignores.is_some();
+        ignores.extend(own_ignores);
         let current_directory = fs::read_dir(path)?;
         
         for item in current_directory {
             let entry = item?;
             let file_name = entry.file_name();
+            let entry_path = entry.path();
+            let is_dir = entry.file_type()?.is_dir();
+            let ignored = is_ignored(ignores, &entry_path, is_dir);
             
-            if entry.file_type()?.is_dir() {
-                if !ign.iter().any(|i| &file_name == i.as_os_str()) {
-                    let new_files = DocGenConfig::get_files_helper(entry.path(), ign)?;
+            if is_dir {
+                if ignored
+                    || filter.ignored_dirs.iter().any(|i| file_name == i.as_os_str())
+                    || filter.is_excluded(&entry_path, true)
+                    || filter.is_output_dir(&entry_path)
+                {
+                    skipped.push(entry_path);
+                } else if file_name != ".git" {
+                    let new_files = DocGenConfig::walk_dir(entry_path, filter, ignores, skipped)?;
                     output.extend(new_files);
                 }
-            } else {
-                let entry_path = entry.path();
-                if !entry_path.starts_with("./.git") && !entry_path.ends_with(".gitignore") {
-                    output.push(entry_path);
-                }
+            } else if !ignored
+                && !entry_path.ends_with(".gitignore")
+                && !filter.is_excluded(&entry_path, false)
+                && filter.is_included(&entry_path)
+            {
+                output.push(entry_path);
             }
         }
+        if pushed {
+            ignores.pop();
+        }
         Ok(output)
     }
-    pub fn create_config(mut dir: PathBuf) -> Result<(), io::Error> {
-        let content = r#"inline_comments = ""
-multi_comments = []
-ignored_dirs = []
-file_extensions = []"#;
+
+    /// Writes a new VexDoc.toml into `dir` with a `[languages.*]` table for each preset
+    ///
+    /// With no presets the file documents C-style sources with top-level settings. Existing configs
+    /// are only replaced when `force` is set.
+    pub fn create_config(
+        mut dir: PathBuf,
+        presets: &[&Preset],
+        ignored_dirs: &[&str],
+        force: bool,
+    ) -> Result<(), io::Error> {
+        let content = presets::render_config(presets, ignored_dirs);
         dir.push("VexDoc.toml");
-        let mut file = File::create_new(&dir)?;
+        let mut file = if force {
+            File::create(&dir)?
+        } else {
+            File::create_new(&dir)?
+        };
         file.write_all(content.as_bytes())?;
         Ok(())
     }
 }
 
+/// Checks a path against a stack of ignore files
+///
+/// The innermost ignore file with an opinion wins, so nested `!pattern`s can re-include paths.
+fn is_ignored(ignores: &[Gitignore], path: &Path, is_dir: bool) -> bool {
+    ignores
+        .iter()
+        .rev()
+        .map(|ignore| ignore.matched(path, is_dir))
+        .find(|m| !m.is_none())
+        .is_some_and(|m| m.is_ignore())
+}
+
+/// Compiles a list of gitignore-style globs, reporting any invalid pattern
+fn compile_globs(
+    patterns: &[String],
+    key: &str,
+    spans: &ConfigSpans,
+    validation_errors: &mut Vec<ValidationError>,
+) -> Option<Gitignore> {
+    if patterns.is_empty() {
+        return None;
+    }
+    // Paths are made relative to the project root before matching, so the builder root is unused
+    let mut builder = GitignoreBuilder::new("");
+    for (i, pattern) in patterns.iter().enumerate() {
+        if let Err(e) = builder.add_line(None, pattern) {
+            validation_errors.push(
+                ValidationError::new(
+                    ErrorCode::InvalidGlob,
+                    format!("Invalid pattern '{}' in {}: {}", pattern, key, e),
+                    format!("Use gitignore-style globs, e.g., {} = [\"src/generated/**\"]", key),
+                )
+                .with_span(spans.item(&[key], i)),
+            );
+        }
+    }
+    builder.build().ok()
+}
+
+/// Checks one set of comment markers, labelling errors with the language profile they came from
+fn validate_syntax(
+    profile: Option<&str>,
+    inline_comments: &str,
+    multi_comments: &[DelimiterPair],
+    spans: &ConfigSpans,
+    validation_errors: &mut Vec<ValidationError>,
+) {
+    let (location, table) = match profile {
+        Some(name) => (format!(" in [languages.{}]", name), vec!["languages", name]),
+        None => (String::new(), vec![]),
+    };
+
+    // Either kind of comment is enough: shell only has line comments, HTML only block comments
+    if multi_comments.is_empty() && inline_comments.is_empty() {
+        validation_errors.push(
+            ValidationError::new(
+                ErrorCode::NoCommentDelimiters,
+                format!("No comment delimiters specified{}", location),
+                "Add an inline comment delimiter, e.g., inline_comments = \"//\", multiline comment delimiters, e.g., multi_comments = [\"/*\", \"*/\"], or both".to_string(),
+            )
+            // Point at whichever key was left empty, or at the language's table
+            .with_span(
+                spans
+                    .key(&[table.as_slice(), &["inline_comments"]].concat())
+                    .or_else(|| spans.key(&[table.as_slice(), &["multi_comments"]].concat()))
+                    .or_else(|| spans.key(&table)),
+            ),
+        );
+    }
+
+    // Validate multiline comment pairs
+    if multi_comments.iter().any(|p| p.open.is_empty() || p.close.is_empty()) {
+        validation_errors.push(
+            ValidationError::new(
+                ErrorCode::IncompletePair,
+                format!("Multiline comments must have both opening and closing delimiters{}", location),
+                "Add both opening and closing delimiters, e.g., multi_comments = [\"/*\", \"*/\"]".to_string(),
+            )
+            .with_span(spans.key(&[table.as_slice(), &["multi_comments"]].concat())),
+        );
+    }
+}
+
 /// Generates HTML documentation from source files
 pub fn document(conf: DocGenConfig, files: Vec<PathBuf>, verbose: bool, quiet: bool) -> Result<(), SubcommandError> {
-    let docs_dir = Path::new("docs");
-    if let Err(e) = DirBuilder::new().create(docs_dir) {
-        match e.kind() {
-            // if it already exists we don't need to worry about it not being created
-            // TODO: Consider refactor and having a genuine error for this?
-            ErrorKind::AlreadyExists => (),
-            _ => return Err(SubcommandError::GenerationError(Box::new(e))),
-        };
+    let docs_dir = &conf.output_dir();
+    // Output dirs like target/vexdoc may need their parents created too
+    if let Err(e) = DirBuilder::new().recursive(true).create(docs_dir) {
+        return Err(SubcommandError::GenerationError(Box::new(e)));
     }
     let new_files: Vec<&Path> = files
         .iter()
@@ -193,39 +960,33 @@ pub fn document(conf: DocGenConfig, files: Vec<PathBuf>, verbose: bool, quiet: b
         pb
     };
 
-    // Process files in parallel
-    let results: Vec<Result<bool, SubcommandError>> = new_files
-        .par_iter()
-        .map(|path| {
-            if verbose {
-                println!("Documenting {} ...", path.display());
-            }
-            pb.set_message(format!("Documenting {}", path.display()));
-                    let result = create_doc(path, &conf, docs_dir);
-            pb.inc(1);
-            if verbose {
-                println!("Done with {}", path.display());
-            }
-            result
-        })
-        .collect();
+    let results = generate(&conf, &new_files, docs_dir, &pb, verbose);
 
     if !quiet {
         pb.finish_with_message("Documentation generation complete!");
     }
 
-    // Collect results and notices
+    // Collect results and notices, keeping every file's errors so they can be reported together
     let mut notices = Vec::<String>::new();
+    let mut errors = Vec::new();
+    let mut warnings = Vec::new();
     for (i, result) in results.into_iter().enumerate() {
         match result {
-            Ok(false) => {
-                notices.push(format!(
-                    "NOTICE: {} contained no annotations, so nothing was actually written to its documentation. Ensure it has correct annotations",
-                    new_files[i].display()
-                ));
+            Ok(file) => {
+                if file.hidden {
+                    notices.push(format!(
+                        "NOTICE: every section of {} is hidden at this visibility, so no page was written for it",
+                        conf.relative_path(new_files[i]).display()
+                    ));
+                } else if !file.has_vexdoc {
+                    notices.push(format!(
+                        "NOTICE: {} contained no annotations, so nothing was actually written to its documentation. Ensure it has correct annotations",
+                        conf.relative_path(new_files[i]).display()
+                    ));
+                }
+                warnings.extend(file.warnings);
             }
-            Err(e) => return Err(e),
-            Ok(true) => {} // File had documentation, no notice needed
+            Err(e) => errors.push(e),
         }
     }
 
@@ -234,159 +995,421 @@ pub fn document(conf: DocGenConfig, files: Vec<PathBuf>, verbose: bool, quiet: b
             println!("{}", notice);
         }
     }
+    // Warnings go to stderr with the errors, even when quiet
+    let renderer = Renderer::for_stderr().with_encoding(conf.fallback_encoding);
+    for warning in &warnings {
+        eprintln!("{}", renderer.render(warning));
+    }
 
-    Ok(())
+    SubcommandError::from_errors(errors)
 }
 
-// Maybe??? give it a try later
-// ok we will, State machine to help determine what exactly to put
-// holy shit thank you me
-#[derive(Debug, Clone, Copy)]
-enum ParserState {
-    Ignore,
-    FileSummary,
-    Title,
-    ItemSummary,
-    Code,
+/// Writes a page for each file, in parallel
+///
+/// Every file is parsed before any page is written, so references between them can be resolved.
+/// Each result holds the file's warnings, or fails with every error in it.
+fn generate(
+    conf: &DocGenConfig,
+    files: &[&Path],
+    docs_dir: &Path,
+    pb: &ProgressBar,
+    verbose: bool,
+) -> Vec<Result<FileCheck, SubcommandError>> {
+    let (contents, reads) = read_sources(conf, files);
+    prepare(conf, files, &contents, reads)
+        .into_par_iter()
+        .map(|document| {
+            let name = conf.relative_path(document.path);
+            if verbose {
+                println!("Documenting {} ...", name.display());
+            }
+            pb.set_message(format!("Documenting {}", name.display()));
+            let result = document.write(docs_dir);
+            pb.inc(1);
+            if verbose {
+                println!("Done with {}", name.display());
+            }
+            result
+        })
+        .collect()
 }
 
-fn create_doc(old_path: &Path, conf: &DocGenConfig, docs_dir: &Path) -> Result<bool, SubcommandError> {
-    let content = fs::read_to_string(old_path).map_err(|e| SubcommandError::FileReadError(e))?;
-    let mut has_vexdoc = false;
-    let mut no_filesummary = false;
-    let single_multiline = conf.multi_comments.get(1).is_none();
-    let filename = old_path.file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or("unknown");
-    
-    let mut body = Container::new(ContainerType::Div)
-        .with_attributes([("class", "container")])
-        .with_header(1, filename);
-
-    let mut state = ParserState::Ignore;
-    let mut included = Vec::<&str>::with_capacity(32); // Pre-allocate for better performance
-    let mut comment_buffer = String::with_capacity(256); // Buffer for comment text
-    let mut code_buffer = String::with_capacity(512); // Buffer for code text
-    
-    // Pre-compute common strings to avoid allocations in hot loop
-    let inline_prefix = format!("{}!", conf.inline_comments);
-    let filesummary_prefix = format!("{}filesummary", conf.multi_comments[0]);
-    let endsummary_suffix = if single_multiline {
-        format!("endsummary{}", conf.multi_comments[0])
-    } else {
-        format!("endsummary{}", conf.multi_comments[1])
-    };
-    let endvexdoc = format!("{}ENDVEXDOC", conf.inline_comments);
-
-    for line in content.lines() {
-        match state {
-            ParserState::Ignore => {
-                if line.starts_with(&inline_prefix) {
-                    no_filesummary = true;
-                    has_vexdoc = true;
-                    state = ParserState::Title;
-                    // Line is guaranteed to have at least n+1 characters due to above check
-                    body.add_header(2, &line[inline_prefix.len()..].trim_start());
-                } else if !no_filesummary && line.starts_with(&filesummary_prefix) {
-                    has_vexdoc = true;
-                    state = ParserState::FileSummary;
-                }
-            }
-            ParserState::FileSummary => {
-                if line.starts_with(&endsummary_suffix) {
-                    comment_buffer.clear();
-                    for (i, line) in included.iter().enumerate() {
-                        if i > 0 {
-                            comment_buffer.push(' ');
-                        }
-                        comment_buffer.push_str(line);
-                    }
-                    body.add_html(
-                        HtmlElement::new(HtmlTag::ParagraphText)
-                            .with_attribute("class", "comment")
-                            .with_child(comment_buffer.clone().into()),
-                    );
-                    included.clear();
-                    state = ParserState::Ignore;
-                } else {
-                    included.push(line);
-                }
+/// The result of checking the annotations of one file
+#[derive(Debug)]
+pub struct FileCheck {
+    pub path: PathBuf,
+    /// Whether the file has any annotations at all
+    pub has_vexdoc: bool,
+    pub errors: Vec<SubcommandError>,
+    /// Lint findings that don't fail the run
+    pub warnings: Vec<SubcommandError>,
+    /// Whether every section is hidden at the chosen visibility, so no page was written
+    pub hidden: bool,
+}
+
+/// Parses and lints files the same way `document` does, without writing anything
+pub fn check(conf: &DocGenConfig, files: Vec<PathBuf>) -> Vec<FileCheck> {
+    let files: Vec<&Path> = files
+        .iter()
+        .map(|p| p.strip_prefix("./").unwrap_or(p))
+        .collect();
+    let (contents, reads) = read_sources(conf, &files);
+    prepare(conf, &files, &contents, reads)
+        .into_iter()
+        .map(|document| FileCheck {
+            path: document.path.into(),
+            has_vexdoc: document.parsed.has_vexdoc(),
+            errors: document.parsed.errors,
+            warnings: document.warnings,
+            hidden: document.hidden,
+        })
+        .collect()
+}
+
+/// A file read, parsed and linted, with its summaries rendered and its references resolved
+struct Document<'a> {
+    path: &'a Path,
+    content: &'a str,
+    /// The path relative to the project root with `/` separators, as written in references
+    name: String,
+    page: String,
+    parsed: ParsedFile<'a>,
+    warnings: Vec<SubcommandError>,
+    /// Whether every section is hidden at the chosen visibility
+    hidden: bool,
+    /// Titles of the sections hidden at the chosen visibility
+    hidden_titles: Vec<&'a str>,
+    /// The `id` of each section's heading
+    anchors: Vec<String>,
+    file_summaries: Vec<String>,
+    /// The rendered summary of each section
+    summaries: Vec<Option<String>>,
+    /// The sections and files that refer to each section
+    backlinks: Vec<Vec<Backlink>>,
+}
+
+/// A reference found in a summary, and which summary it was in
+struct Reference {
+    /// The section whose summary has it, or `None` for the file summary
+    section: Option<usize>,
+    target: String,
+    span: Span,
+}
+
+/// Reads every file, keeping the contents apart from the warnings about each file, or why it
+/// could not be read, since parsed files borrow the contents
+fn read_sources(
+    conf: &DocGenConfig,
+    files: &[&Path],
+) -> (Vec<String>, Vec<Result<Vec<SubcommandError>, SubcommandError>>) {
+    files
+        .par_iter()
+        .map(|path| {
+            let bytes = conf
+                .require_syntax(path)
+                .and_then(|_| fs::read(path).map_err(SubcommandError::FileReadError));
+            let decoded = match bytes {
+                Ok(bytes) => decode(&bytes, conf.fallback_encoding),
+                Err(e) => return (String::new(), Err(e)),
+            };
+            // Invalid bytes are shown replaced rather than failing the whole run
+            let warnings = match decoded.lossy {
+                true => vec![SubcommandError::UserError {
+                    code: ErrorCode::LossyDecoding,
+                    causes: format!(
+                        "this file is not valid {}, so its invalid bytes are shown as \u{FFFD}",
+                        decoded.encoding.name()
+                    ),
+                    source: None,
+                    kind: UserErrorKind::Warning,
+                    file: path.into(),
+                    span: decoded.text.find('\u{FFFD}').map(|i| Span::from_offset(&decoded.text, i)),
+                    help: Some("set `encoding` to the encoding the file is saved in, e.g. encoding = \"windows-1252\", or convert it to UTF-8".into()),
+                }],
+                false => Vec::new(),
+            };
+            (decoded.text, Ok(warnings))
+        })
+        .unzip()
+}
+
+/// Parses and lints every file, then renders their summaries and resolves the references between them
+///
+/// Files with errors get no page, so references to them are unresolved.
+fn prepare<'a>(
+    conf: &DocGenConfig,
+    files: &[&'a Path],
+    contents: &'a [String],
+    reads: Vec<Result<Vec<SubcommandError>, SubcommandError>>,
+) -> Vec<Document<'a>> {
+    let mut documents: Vec<Document> = files
+        .par_iter()
+        .zip(contents)
+        .zip(reads)
+        .map(|((path, content), read)| {
+            let (mut parsed, mut warnings) = match (read, conf.require_syntax(path)) {
+                (Ok(warnings), Ok(syntax)) => (parse(path, content, &syntax, conf.allow_unterminated), warnings),
+                (Err(e), _) | (Ok(_), Err(e)) => (
+                    ParsedFile {
+                        errors: e.into_errors(),
+                        ..ParsedFile::default()
+                    },
+                    Vec::new(),
+                ),
+            };
+            warnings.extend(lint_file(path, &mut parsed, conf));
+            // Hidden sections are dropped before anything links to them, so none of them leaks,
+            // but their titles are kept so links to them become plain text rather than warnings
+            let titles: Vec<&str> = parsed.sections.iter().map(|s| s.title).collect();
+            let hidden = parsed.errors.is_empty() && parsed.hide(conf.visibility);
+            let hidden_titles = titles
+                .into_iter()
+                .filter(|title| !parsed.sections.iter().any(|s| s.title == *title))
+                .collect();
+            let anchors = anchors(parsed.sections.iter().map(|s| s.title));
+            Document {
+                path,
+                content,
+                name: conf.relative_path(path).display().to_string().replace('\\', "/"),
+                page: conf.page_name(path),
+                warnings,
+                hidden,
+                hidden_titles,
+                file_summaries: Vec::new(),
+                summaries: Vec::new(),
+                backlinks: vec![Vec::new(); anchors.len()],
+                anchors,
+                parsed,
             }
-            ParserState::Title => {
-                let startsummary_prefix = format!("{}startsummary", conf.multi_comments[0]);
-                if line.starts_with(&startsummary_prefix) {
-                    state = ParserState::ItemSummary;
-                } else {
-                    return Err(SubcommandError::UserError {
-                        causes: "section titles must be followed by a summary".into(),
-                        source: None,
-                        kind: UserErrorKind::Annotations,
-                        file: old_path.into(),
+        })
+        .collect();
+
+    let mut index = Index::default();
+    for document in documents.iter().filter(|d| d.parsed.errors.is_empty()) {
+        let titles = document.parsed.sections.iter().map(|s| s.title);
+        let anchors = document.anchors.iter().map(String::as_str);
+        index.add(&document.name, &document.page, titles.zip(anchors), &document.hidden_titles);
+    }
+    let references: Vec<Vec<Reference>> = documents
+        .par_iter_mut()
+        .map(|document| match document.parsed.errors.is_empty() {
+            true => document.render_summaries(&index, conf.summary_format),
+            false => Vec::new(),
+        })
+        .collect();
+
+    // Each resolved reference is a backlink on the section it points at
+    let pages: HashMap<&str, usize> = documents.iter().enumerate().map(|(i, d)| (d.page.as_str(), i)).collect();
+    let mut backlinks = Vec::new();
+    let mut unresolved = Vec::new();
+    for (i, references) in references.into_iter().enumerate() {
+        let document = &documents[i];
+        let mut missing = Vec::new();
+        for reference in references {
+            let anchor = match index.resolve(&document.name, &reference.target) {
+                Ok(anchor) => anchor,
+                Err(suggestion) => {
+                    missing.push(Unresolved {
+                        section: reference.section,
+                        target: reference.target,
+                        span: reference.span,
+                        suggestion,
                     });
+                    continue;
                 }
-            }
-            ParserState::ItemSummary => {
-                if line.starts_with(&endsummary_suffix) {
-                    comment_buffer.clear();
-                    for (i, line) in included.iter().enumerate() {
-                        if i > 0 {
-                            comment_buffer.push(' ');
-                        }
-                        comment_buffer.push_str(line);
-                    }
-                    body.add_html(
-                        HtmlElement::new(HtmlTag::ParagraphText)
-                            .with_attribute("class", "comment")
-                            .with_child(comment_buffer.clone().into()),
-                    );
-                    included.clear();
-                    state = ParserState::Code;
-                } else {
-                    included.push(line);
+            };
+            let from = Anchor {
+                page: document.page.clone(),
+                id: reference.section.map(|s| document.anchors[s].clone()).unwrap_or_default(),
+            };
+            let label = match reference.section.map(|s| document.parsed.sections[s].title) {
+                Some(title) if anchor.page == document.page => title.to_string(),
+                Some(title) => format!("{} ({})", title, document.name),
+                None => document.name.clone(),
+            };
+            let target = pages[anchor.page.as_str()];
+            if let Some(section) = documents[target].anchors.iter().position(|id| *id == anchor.id) {
+                if from != anchor {
+                    backlinks.push((target, section, Backlink { from, label }));
                 }
             }
-            ParserState::Code => {
-                if line.replace(" ", "").starts_with(&endvexdoc) {
-                    code_buffer.clear();
-                    for (i, line) in included.iter().enumerate() {
-                        if i > 0 {
-                            code_buffer.push('\n');
-                        }
-                        code_buffer.push_str(line);
-                    }
-                    body.add_html(HtmlElement::new(HtmlTag::PreformattedText).with_html(
-                        HtmlElement::new(HtmlTag::CodeText).with_child(code_buffer.clone().into()),
-                    ));
-                    included.clear();
-                    state = ParserState::Ignore;
-                } else {
-                    included.push(line);
-                }
+        }
+        unresolved.push(missing);
+    }
+    for (target, section, backlink) in backlinks {
+        let list = &mut documents[target].backlinks[section];
+        if !list.contains(&backlink) {
+            list.push(backlink);
+        }
+    }
+    for (document, missing) in documents.iter_mut().zip(unresolved) {
+        let (warnings, denied): (Vec<_>, Vec<_>) = lints::unresolved(document.path, &document.parsed, &missing, &conf.lints)
+            .into_iter()
+            .partition(SubcommandError::is_warning);
+        document.warnings.extend(warnings);
+        document.parsed.errors.extend(denied);
+    }
+    documents
+}
+
+impl Document<'_> {
+    /// Renders every summary, returning the references found in them
+    fn render_summaries(&mut self, index: &Index, format: SummaryFormat) -> Vec<Reference> {
+        let links = Links::new(index, &self.name, &self.page);
+        let mut references = Vec::new();
+        let mut found = |section: Option<usize>, summary: &Summary| {
+            for target in links.take() {
+                let span = reference_span(self.content, summary, &target);
+                references.push(Reference { section, target, span });
+            }
+        };
+        for summary in &self.parsed.file_summaries {
+            self.file_summaries.push(render_summary(summary, format, &links));
+            found(None, summary);
+        }
+        for (i, section) in self.parsed.sections.iter().enumerate() {
+            let html = section.summary.as_ref().map(|summary| {
+                let html = render_summary(summary, format, &links);
+                found(Some(i), summary);
+                html
+            });
+            self.summaries.push(html);
+        }
+        references
+    }
+
+    /// Writes the page, failing with every error in the file
+    ///
+    /// On success, the returned check holds the file's warnings.
+    fn write(self, docs_dir: &Path) -> Result<FileCheck, SubcommandError> {
+        let has_vexdoc = self.parsed.has_vexdoc();
+        SubcommandError::from_errors(self.parsed.errors)?;
+        if self.hidden {
+            // A page from an earlier build could still show what is now hidden
+            match fs::remove_file(docs_dir.join(&self.page)) {
+                Err(e) if e.kind() != ErrorKind::NotFound => return Err(SubcommandError::GenerationWriteError(e)),
+                _ => {}
+            }
+            return Ok(FileCheck {
+                path: self.path.into(),
+                has_vexdoc,
+                errors: Vec::new(),
+                warnings: self.warnings,
+                hidden: true,
+            });
+        }
+        let filename = self.path.file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("unknown");
+
+        let mut body = Container::new(ContainerType::Div)
+            .with_attributes([("class", "container")])
+            .with_header(1, filename);
+        for summary in &self.file_summaries {
+            body.add_raw(summary);
+        }
+        for (i, section) in self.parsed.sections.iter().enumerate() {
+            // build_html inserts heading text as it is, and titles taken from code have `<` and `&`
+            let badge = match section.internal {
+                Some(true) => " <span class=\"visibility\">internal</span>",
+                _ => "",
+            };
+            let title = format!("{}{}", escape_html(section.title), badge);
+            body.add_header_attr(2, title, [("id", self.anchors[i].as_str())]);
+            if let Some(signature) = &section.signature {
+                body.add_raw(format!(
+                    "<pre class=\"signature\"><code>{}</code></pre>",
+                    escape_html(&signature.text)
+                ));
+            }
+            if let Some(summary) = &self.summaries[i] {
+                body.add_raw(summary);
+            }
+            if let Some(code) = &section.code {
+                body.add_html(code_block(code));
+            }
+            if !self.backlinks[i].is_empty() {
+                body.add_raw(backlinks_html(&self.backlinks[i], &self.page));
             }
         }
+
+        // This should never fail
+        // TODO: Ensure this never fails
+        fs::write(
+            docs_dir.join(&self.page),
+            doc_boilerplate_memo(&self.path)
+                .with_container(body)
+                .with_script_literal(r#"hljs.highlightAll();"#)
+                .to_html_string(),
+        )
+        .map_err(SubcommandError::GenerationWriteError)?;
+        Ok(FileCheck {
+            path: self.path.into(),
+            has_vexdoc,
+            errors: Vec::new(),
+            warnings: self.warnings,
+            hidden: false,
+        })
     }
+}
 
-    // This should never fail
-    // TODO: Ensure this never fails
+/// Lints a parsed file, adding the findings of denied rules to its errors and returning the rest
+///
+/// Files that failed to parse are not linted, since what was read of them may be incomplete.
+fn lint_file(path: &Path, parsed: &mut ParsedFile, conf: &DocGenConfig) -> Vec<SubcommandError> {
+    if !parsed.errors.is_empty() {
+        return Vec::new();
+    }
+    let (warnings, denied) = lint(path, parsed, &conf.lints)
+        .into_iter()
+        .partition(SubcommandError::is_warning);
+    parsed.errors = denied;
+    warnings
+}
 
-            fs::write(
-                docs_dir
-                    .join(
-                        old_path
-                            .display()
-                            .to_string()
-                            .replace(".", "-")
-                            .replace("/", "_")
-                            .replace("\\", "_"),
-                    )
-                    .with_extension("html"),
-        doc_boilerplate_memo(&old_path)
-            .with_container(body)
-            .with_script_literal(r#"hljs.highlightAll();"#)
-            .to_html_string(),
-    )
-    .map_err(|e| SubcommandError::GenerationWriteError(e))?;
-    Ok(has_vexdoc)
+/// The "Referenced by" list under a section
+fn backlinks_html(backlinks: &[Backlink], page: &str) -> String {
+    let links: Vec<String> = backlinks
+        .iter()
+        .map(|b| format!("<a href=\"{}\">{}</a>", escape_html(&b.from.href(page)), escape_html(&b.label)))
+        .collect();
+    format!("<p class=\"backlinks\"><strong>Referenced by:</strong> {}</p>", links.join(", "))
+}
+
+/// Renders a summary, either as CommonMark or as a single paragraph of plain text
+fn summary_html(lines: &[&str], format: SummaryFormat, links: &Links) -> String {
+    match format {
+        SummaryFormat::Plain => summary_paragraph(&links.plain(&lines.join(" "), escape_html)).to_html_string(),
+        SummaryFormat::Markdown => format!("<div class=\"comment markdown\">{}</div>", markdown_html(&lines.join("\n"), links)),
+    }
+}
+
+fn markdown_html(text: &str, links: &Links) -> String {
+    let mut html = String::new();
+    let events = pulldown_cmark::Parser::new_ext(text, pulldown_cmark::Options::ENABLE_WIKILINKS);
+    pulldown_cmark::html::push_html(&mut html, links.markdown(events).into_iter());
+    html
+}
+
+/// Escapes text for use in HTML, including inside attribute values
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// A summary joined into one paragraph
+fn summary_paragraph(text: &str) -> HtmlElement {
+    HtmlElement::new(HtmlTag::ParagraphText)
+        .with_attribute("class", "comment")
+        .with_child(text.into())
+}
+
+/// Renders the code of a section
+fn code_block(lines: &[&str]) -> HtmlElement {
+    HtmlElement::new(HtmlTag::PreformattedText)
+        .with_html(HtmlElement::new(HtmlTag::CodeText).with_child(escape_html(&lines.join("\n")).into()))
 }
 
 fn doc_boilerplate_memo(path: &impl Deref<Target = Path>) -> HtmlPage {
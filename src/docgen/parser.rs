@@ -0,0 +1,727 @@
+/*filesummary
+Finds titles, summaries and code in a source file without rendering anything, so generating and checking share one parser.
+endsummary*/
+
+use std::mem;
+use std::path::Path;
+
+use super::lints::allow_directive;
+use super::signature::{derived_title, is_attribute, signature, Signature};
+use super::tags::{SummaryBuilder, Tag};
+use super::{BlockEnd, DelimiterPair, Markers, Visibility};
+use crate::codes::ErrorCode;
+use crate::errors::{Span, SubcommandError, UserErrorKind};
+
+/// How the summary being read was opened
+#[derive(Debug, Clone, Copy)]
+enum SummaryStyle<'a> {
+    /// Inside a block comment, closed by `endsummary` and the pair's closing delimiter
+    Block(&'a DelimiterPair),
+    /// A run of line comments, closed by a line comment holding `endsummary`
+    Line,
+}
+
+/// What a line means while a summary is being read
+enum SummaryLine<'a> {
+    Text(&'a str),
+    End,
+    /// A line-comment summary ran into a line that is not a comment
+    Unterminated,
+}
+
+/// The comment markers used to parse a single file
+#[derive(Debug, Clone, Copy)]
+pub(super) struct CommentSyntax<'a> {
+    pub(super) inline_comments: &'a str,
+    pub(super) multi_comments: &'a [DelimiterPair],
+    pub(super) nested_comments: bool,
+    /// String literals that can span lines, whose contents are never read as markers
+    pub(super) multiline_strings: &'a [DelimiterPair],
+    pub(super) markers: &'a Markers,
+    pub(super) block_end: BlockEnd,
+}
+
+impl<'a> CommentSyntax<'a> {
+    /// Reads a section title, e.g. `//! Title`, or `<!--! Title -->` for languages without
+    /// line comments
+    fn title<'l>(&self, line: &'l str) -> Option<&'l str> {
+        let line = line.trim_start();
+        let title = &self.markers.title;
+        if !self.inline_comments.is_empty() {
+            return line
+                .strip_prefix(self.inline_comments)?
+                .strip_prefix(title.as_str())
+                .map(str::trim);
+        }
+        self.multi_comments.iter().find_map(|pair| {
+            let rest = line.strip_prefix(pair.open.as_str())?.strip_prefix(title.as_str())?.trim();
+            Some(rest.strip_suffix(pair.close.as_str()).unwrap_or(rest).trim_end())
+        })
+    }
+
+    /// Whether a line ends a section, e.g. `// ENDVEXDOC` or `<!-- ENDVEXDOC -->`
+    fn is_end(&self, line: &str) -> bool {
+        let line = line.trim_start().replace(' ', "");
+        let end = self.markers.end.replace(' ', "");
+        if !self.inline_comments.is_empty() {
+            return line.starts_with(&format!("{}{}", self.inline_comments, end));
+        }
+        self.multi_comments
+            .iter()
+            .any(|pair| line.starts_with(&format!("{}{}", pair.open.replace(' ', ""), end)))
+    }
+
+    /// Checks whether a line opens a summary with the given keyword, in either comment style
+    fn summary_start(&self, line: &str, keyword: &str) -> Option<SummaryStyle<'a>> {
+        let line = line.trim_start();
+        if let Some(pair) = self
+            .multi_comments
+            .iter()
+            .find(|p| strip_marker(line, &p.open, keyword).is_some())
+        {
+            return Some(SummaryStyle::Block(pair));
+        }
+        if !self.inline_comments.is_empty() && strip_marker(line, self.inline_comments, keyword).is_some() {
+            return Some(SummaryStyle::Line);
+        }
+        None
+    }
+
+    /// Every comment delimiter, opening and closing
+    fn delimiters(&self) -> Vec<&'a str> {
+        let pairs = self.multi_comments.iter().flat_map(|p| [p.open.as_str(), p.close.as_str()]);
+        std::iter::once(self.inline_comments).chain(pairs).collect()
+    }
+
+    /// The line that ends a section, as the user would write it
+    pub(super) fn end_marker(&self) -> String {
+        match self.multi_comments.first() {
+            Some(pair) if self.inline_comments.is_empty() => {
+                format!("{} {} {}", pair.open, self.markers.end, pair.close)
+            }
+            _ => format!("{} {}", self.inline_comments, self.markers.end),
+        }
+    }
+
+    /// The line that ends a summary opened in the given style
+    fn summary_end_marker(&self, style: SummaryStyle) -> String {
+        match style {
+            SummaryStyle::Block(pair) => format!("{}{}", self.markers.end_summary, pair.close),
+            SummaryStyle::Line => format!("{} {}", self.inline_comments, self.markers.end_summary),
+        }
+    }
+
+    /// Whether a line is an ordinary comment, i.e. one that opens with either kind of delimiter
+    pub(super) fn is_comment(&self, line: &str) -> bool {
+        let line = line.trim_start();
+        (!self.inline_comments.is_empty() && line.starts_with(self.inline_comments))
+            || self.multi_comments.iter().any(|p| line.starts_with(p.open.as_str()))
+    }
+
+    /// Follows the `multiline_strings` through a line, given the string it starts in, returning
+    /// the string it ends in
+    ///
+    /// A line comment ends the scan and block comments on the line are skipped, so quotes in
+    /// comments never open a string. A backslash escapes the next character in strings closed by
+    /// quotes alone, but not in raw strings such as `r#"..."#`.
+    fn string_after(&self, line: &str, mut open: Option<&'a DelimiterPair>) -> Option<&'a DelimiterPair> {
+        let quote_strings = self.multiline_strings.iter().any(|p| p.open.starts_with('\''));
+        let mut i = 0;
+        while let Some(c) = line[i..].chars().next() {
+            let rest = &line[i..];
+            let skip = match open {
+                Some(string) if c == '\\' && string.close.chars().all(|c| "\"'`".contains(c)) => {
+                    1 + rest[1..].chars().next().map_or(0, char::len_utf8)
+                }
+                Some(string) if rest.starts_with(string.close.as_str()) => {
+                    open = None;
+                    string.close.len()
+                }
+                Some(_) => c.len_utf8(),
+                None if !self.inline_comments.is_empty() && rest.starts_with(self.inline_comments) => break,
+                // An escaped quote outside a string, as in the shell's `'it'\''s'`
+                None if c == '\\' => 1 + rest[1..].chars().next().map_or(0, char::len_utf8),
+                None => {
+                    let comment = self.multi_comments.iter().find(|p| rest.starts_with(p.open.as_str()));
+                    let string = self
+                        .multiline_strings
+                        .iter()
+                        .filter(|p| rest.starts_with(p.open.as_str()))
+                        .max_by_key(|p| p.open.len());
+                    // `'"'` is a character, not the start of a string, unless `'` quotes strings too
+                    let character = ["'\"'", "'\\\"'"].into_iter().find(|ch| !quote_strings && rest.starts_with(ch));
+                    if let Some(comment) = comment {
+                        match rest[comment.open.len()..].find(comment.close.as_str()) {
+                            Some(end) => comment.open.len() + end + comment.close.len(),
+                            None => break,
+                        }
+                    } else if let Some(character) = character {
+                        character.len()
+                    } else if let Some(string) = string {
+                        open = Some(string);
+                        string.open.len()
+                    } else {
+                        c.len_utf8()
+                    }
+                }
+            };
+            i += skip;
+        }
+        open
+    }
+
+    /// The pair of a block comment that this line opens but does not close
+    fn opens_block(&self, line: &str) -> Option<&'a DelimiterPair> {
+        let line = line.trim_start();
+        self.multi_comments.iter().find(|p| {
+            line.strip_prefix(p.open.as_str())
+                .is_some_and(|rest| !rest.contains(p.close.as_str()))
+        })
+    }
+
+    /// Classifies a line inside a summary, tracking the nesting `depth` of block comments
+    ///
+    /// `indent` is the indentation of the line that opened the summary and is removed from its text.
+    fn summary_line<'l>(
+        &self,
+        style: SummaryStyle,
+        depth: &mut isize,
+        indent: &str,
+        line: &'l str,
+    ) -> SummaryLine<'l> {
+        match style {
+            SummaryStyle::Block(pair) => {
+                // Only the delimiter that opened the summary is still open at depth 1
+                if *depth == 1 && is_summary_end(line.trim_start(), &self.markers.end_summary, &pair.close) {
+                    return SummaryLine::End;
+                }
+                if self.nested_comments {
+                    *depth += pair.depth_change(line);
+                }
+                SummaryLine::Text(dedent(line, indent))
+            }
+            SummaryStyle::Line => {
+                if strip_marker(line.trim_start(), self.inline_comments, &self.markers.end_summary).is_some() {
+                    return SummaryLine::End;
+                }
+                match line.trim_start().strip_prefix(self.inline_comments) {
+                    // Drop the single space that usually follows the comment delimiter
+                    Some(text) => SummaryLine::Text(text.strip_prefix(' ').unwrap_or(text)),
+                    None => SummaryLine::Unterminated,
+                }
+            }
+        }
+    }
+}
+
+/// Follows the code of a section to find where it ends when `block_end` is not `explicit`
+#[derive(Debug, Default)]
+struct BlockTracker {
+    /// The indentation of the first line of code
+    base: Option<usize>,
+    /// Whether a line other than a comment, attribute or decorator has been read, so decorators
+    /// stay with the declaration under them
+    started: bool,
+    /// How many brackets are open, or only braces for `braces`
+    depth: isize,
+    /// Whether a brace has been opened yet
+    opened: bool,
+}
+
+impl BlockTracker {
+    /// Whether the code ends before this line, which is then read as if outside any section
+    fn ends_before(&self, syntax: &CommentSyntax, line: &str) -> bool {
+        match syntax.block_end {
+            BlockEnd::Explicit => false,
+            // Every implicit mode ends at the next title, so a block never takes in the rest of the file
+            _ if syntax.title(line).is_some() => true,
+            BlockEnd::Indent => {
+                !line.trim().is_empty()
+                    && self.started
+                    && self.depth <= 0
+                    && self.base.is_some_and(|base| indentation(line).len() <= base)
+            }
+            BlockEnd::NextTitle | BlockEnd::Braces => false,
+        }
+    }
+
+    /// Takes in a line of code, returning whether the code ends with it
+    fn ends_after(&mut self, syntax: &CommentSyntax, line: &str) -> bool {
+        if line.trim().is_empty() {
+            return false;
+        }
+        self.base.get_or_insert(indentation(line).len());
+        self.started |= !syntax.is_comment(line) && !is_attribute(line.trim());
+        match syntax.block_end {
+            BlockEnd::Explicit | BlockEnd::NextTitle => false,
+            BlockEnd::Indent => {
+                let (opened, closed) = brackets(line, syntax.inline_comments, "([{", ")]}");
+                self.depth += opened - closed;
+                false
+            }
+            BlockEnd::Braces => {
+                let (opened, closed) = brackets(line, syntax.inline_comments, "{", "}");
+                self.depth += opened - closed;
+                self.opened |= opened > 0;
+                // A declaration without a body, such as a constant or a prototype, ends at its `;`
+                match self.opened {
+                    true => self.depth <= 0,
+                    false => !syntax.is_comment(line) && line.trim_end().ends_with(';'),
+                }
+            }
+        }
+    }
+}
+
+// Maybe??? give it a try later
+// ok we will, State machine to help determine what exactly to put
+// holy shit thank you me
+#[derive(Debug, Clone, Copy)]
+enum ParserState {
+    Ignore,
+    FileSummary,
+    Title,
+    ItemSummary,
+    Code,
+}
+
+/// The file summary or a section's summary
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Summary<'a> {
+    /// The description, one entry per line, with the marker's indentation removed
+    pub(super) lines: Vec<&'a str>,
+    /// The `@name` tags after the description
+    pub(super) tags: Vec<Tag<'a>>,
+    /// Where the summary's opening marker is
+    pub(super) span: Span,
+}
+
+impl Summary<'_> {
+    /// Whether an `@internal` or `@public` tag says who the documentation is for
+    pub(super) fn internal(&self) -> Option<bool> {
+        self.tags.iter().rev().find_map(|tag| match tag.name {
+            "internal" => Some(true),
+            "public" => Some(false),
+            _ => None,
+        })
+    }
+}
+
+/// A titled section and what was written under it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Section<'a> {
+    /// The title after the marker, or taken from the code when the marker has none
+    pub(super) title: &'a str,
+    /// Where the title marker is
+    pub(super) span: Span,
+    pub(super) summary: Option<Summary<'a>>,
+    /// The code of the section, with the indentation of its title removed
+    pub(super) code: Option<Vec<&'a str>>,
+    /// The declaration the code starts with
+    pub(super) signature: Option<Signature<'a>>,
+    /// Lints turned off for this section with `vexdoc-allow(rule)`
+    pub(super) allowed: Vec<&'a str>,
+    /// Whether the section is internal, public, or left to the file's visibility
+    pub(super) internal: Option<bool>,
+}
+
+/// The annotations found in one file, and every problem with them
+#[derive(Debug, Default)]
+pub(super) struct ParsedFile<'a> {
+    pub(super) file_summaries: Vec<Summary<'a>>,
+    pub(super) sections: Vec<Section<'a>>,
+    /// Lints turned off for the whole file with `vexdoc-allow(rule)` outside every section
+    pub(super) allowed: Vec<&'a str>,
+    /// Every rule named in a `vexdoc-allow(rule)`, and where, so misspelt ones can be reported
+    pub(super) allow_names: Vec<(&'a str, Span)>,
+    pub(super) errors: Vec<SubcommandError>,
+}
+
+impl<'a> ParsedFile<'a> {
+    /// Whether the file has any annotations at all
+    pub(super) fn has_vexdoc(&self) -> bool {
+        !self.file_summaries.is_empty() || !self.sections.is_empty()
+    }
+
+    /// Stores a finished summary with the file, or with the section it was written under
+    fn add_summary(&mut self, state: ParserState, summary: Summary<'a>) {
+        match (state, self.sections.last_mut()) {
+            (ParserState::ItemSummary, Some(section)) => section.summary = Some(summary),
+            _ => self.file_summaries.push(summary),
+        }
+    }
+
+    /// Drops the file summary and sections not shown at the given visibility, returning whether
+    /// the file had annotations and is left with no sections to show
+    ///
+    /// Sections without a tag of their own take the file summary's, and are public without one.
+    pub(super) fn hide(&mut self, visibility: Visibility) -> bool {
+        let had_vexdoc = self.has_vexdoc();
+        let had_sections = !self.sections.is_empty();
+        let file_internal = self.file_summaries.iter().find_map(Summary::internal).unwrap_or(false);
+        if !visibility.shows(file_internal) {
+            self.file_summaries.clear();
+        }
+        for section in &mut self.sections {
+            section.internal = Some(section.internal.unwrap_or(file_internal));
+        }
+        self.sections.retain(|s| visibility.shows(s.internal == Some(true)));
+        (had_sections && self.sections.is_empty()) || (had_vexdoc && !self.has_vexdoc())
+    }
+
+    fn add_code(&mut self, code: Vec<&'a str>) {
+        if let Some(section) = self.sections.last_mut() {
+            section.code = Some(code);
+        }
+    }
+
+    /// Stores the code of a block that ended without an `ENDVEXDOC` line, leaving out the blank
+    /// lines before whatever ended it
+    ///
+    /// A block with no code is a section of prose only.
+    fn close_block(&mut self, mut code: Vec<&'a str>) {
+        while code.last().is_some_and(|l| l.trim().is_empty()) {
+            code.pop();
+        }
+        if !code.is_empty() {
+            self.add_code(code);
+        }
+    }
+}
+
+/// Finds the annotations in a file's contents
+///
+/// Every problem in the file is reported, so the parser recovers and keeps going after each one.
+/// `path` is only used to say where errors are.
+pub(super) fn parse<'a>(path: &Path, content: &'a str, syntax: &CommentSyntax, allow_unterminated: bool) -> ParsedFile<'a> {
+    let mut parsed = ParsedFile::default();
+    let mut no_filesummary = false;
+    let mut state = ParserState::Ignore;
+    // The title of the current section and the line its block started on, for errors at the end of the file
+    let mut title_text = "";
+    let mut block_start = Span::new(1, 1);
+    let mut summary_start = Span::new(1, 1);
+    // How the current summary was opened, and how deeply its block comments are nested
+    let mut style = SummaryStyle::Line;
+    let mut depth: isize = 1;
+    // Indentation of the marker that opened the current block, removed from the lines inside it
+    let mut indent = "";
+    // The multi-line string literal the previous line ended inside, and where it started
+    let mut in_string: Option<&DelimiterPair> = None;
+    let mut string_start = Span::new(1, 1);
+    // Lines between a title and its summary, and the ordinary block comment among them still open
+    let mut pending = Vec::<&str>::new();
+    let mut open_comment: Option<&DelimiterPair> = None;
+    let mut included = Vec::<&str>::with_capacity(32); // Pre-allocate for better performance
+    let mut summary = SummaryBuilder::default();
+    let mut block = BlockTracker::default();
+    let markers = syntax.markers;
+    let delimiters = syntax.delimiters();
+    let unterminated_summary = |line_number: usize, line: &str| SubcommandError::UserError {
+        code: ErrorCode::UnterminatedLineSummary,
+        causes: "this line ends a summary written as line comments without closing it".into(),
+        source: None,
+        kind: UserErrorKind::Annotations,
+        file: path.into(),
+        span: Some(marker_span(line_number, line)),
+        help: Some(format!(
+            "summaries written as line comments must end with a `{} {}` line",
+            syntax.inline_comments, markers.end_summary
+        )),
+    };
+
+    for (line_number, line) in content.lines().enumerate() {
+        let outside_string = in_string.is_none();
+        if line_number == 0 && markers.ignore_shebang && is_shebang(line) {
+            continue;
+        }
+        // `vexdoc-allow(rule)` turns lints off for the section it is in, or for the whole file
+        // outside every section
+        if let Some(rules) = allow_directive(line, &delimiters).filter(|_| in_string.is_none()) {
+            parsed.allow_names.extend(rules.iter().map(|rule| {
+                let column = rule.as_ptr() as usize - line.as_ptr() as usize;
+                (*rule, Span::new(line_number + 1, line[..column].chars().count() + 1))
+            }));
+            match state {
+                ParserState::Ignore | ParserState::FileSummary => parsed.allowed.extend(rules),
+                _ => {
+                    if let Some(section) = parsed.sections.last_mut() {
+                        section.allowed.extend(rules);
+                    }
+                }
+            }
+            continue;
+        }
+        // Summaries are optional, so anything after a title that is not blank, a comment or a
+        // summary starts the section's code
+        if let ParserState::Title = state {
+            let skipped = open_comment.is_some()
+                || line.trim().is_empty()
+                || (syntax.title(line).is_none() && !syntax.is_end(line) && syntax.is_comment(line));
+            if !skipped && syntax.summary_start(line, &markers.start_summary).is_none() {
+                included.extend(pending.drain(..).skip_while(|l| l.trim().is_empty()));
+                state = ParserState::Code;
+            }
+        }
+        if let ParserState::Code = state {
+            if in_string.is_none() && block.ends_before(syntax, line) {
+                parsed.close_block(mem::take(&mut included));
+                state = ParserState::Ignore;
+            }
+        }
+        match state {
+            ParserState::Ignore => {
+                if in_string.is_some() {
+                    in_string = syntax.string_after(line, in_string);
+                } else if let Some(title) = syntax.title(line) {
+                    let (internal, title) = visibility_tag(title);
+                    no_filesummary = true;
+                    state = ParserState::Title;
+                    title_text = title;
+                    block_start = marker_span(line_number, line);
+                    indent = indentation(line);
+                    block = BlockTracker::default();
+                    parsed.sections.push(Section {
+                        title,
+                        span: block_start,
+                        summary: None,
+                        code: None,
+                        signature: None,
+                        allowed: Vec::new(),
+                        internal,
+                    });
+                } else if let Some(opened) = syntax
+                    .summary_start(line, &markers.file_summary)
+                    .filter(|_| !no_filesummary)
+                {
+                    state = ParserState::FileSummary;
+                    block_start = marker_span(line_number, line);
+                    summary_start = block_start;
+                    style = opened;
+                    depth = 1;
+                    indent = indentation(line);
+                } else {
+                    in_string = syntax.string_after(line, None);
+                }
+            }
+            ParserState::FileSummary => match syntax.summary_line(style, &mut depth, indent, line) {
+                SummaryLine::End => {
+                    parsed.add_summary(state, summary.finish(summary_start));
+                    state = ParserState::Ignore;
+                }
+                SummaryLine::Text(text) => summary.push(text, line_number, line),
+                SummaryLine::Unterminated => {
+                    parsed.errors.push(unterminated_summary(line_number, line));
+                    parsed.add_summary(state, summary.finish(summary_start));
+                    state = ParserState::Ignore;
+                }
+            },
+            ParserState::Title => {
+                if let Some(pair) = open_comment {
+                    if line.contains(pair.close.as_str()) {
+                        open_comment = None;
+                    }
+                    pending.push(dedent(line, indent));
+                } else if let Some(opened) = syntax.summary_start(line, &markers.start_summary) {
+                    pending.clear();
+                    state = ParserState::ItemSummary;
+                    summary_start = marker_span(line_number, line);
+                    style = opened;
+                    depth = 1;
+                    indent = indentation(line);
+                } else {
+                    open_comment = syntax.opens_block(line);
+                    pending.push(dedent(line, indent));
+                }
+            }
+            ParserState::ItemSummary => match syntax.summary_line(style, &mut depth, indent, line) {
+                SummaryLine::End => {
+                    parsed.add_summary(state, summary.finish(summary_start));
+                    state = ParserState::Code;
+                }
+                SummaryLine::Text(text) => summary.push(text, line_number, line),
+                // The summary ends here, and this line is the first line of code
+                SummaryLine::Unterminated => {
+                    parsed.errors.push(unterminated_summary(line_number, line));
+                    parsed.add_summary(state, summary.finish(summary_start));
+                    in_string = syntax.string_after(line, None);
+                    included.push(dedent(line, indent));
+                    state = ParserState::Code;
+                }
+            },
+            ParserState::Code => {
+                if in_string.is_none() && syntax.is_end(line) {
+                    parsed.add_code(mem::take(&mut included));
+                    state = ParserState::Ignore;
+                } else {
+                    in_string = syntax.string_after(line, in_string);
+                    included.push(dedent(line, indent));
+                    if block.ends_after(syntax, line) {
+                        parsed.close_block(mem::take(&mut included));
+                        state = ParserState::Ignore;
+                    }
+                }
+            }
+        }
+        if outside_string && in_string.is_some() {
+            string_start = marker_span(line_number, line);
+        }
+    }
+
+    // Markers after a string that never closes were read as part of it, so say why they are missing
+    if in_string.is_some() {
+        parsed.errors.push(SubcommandError::UserError {
+            code: ErrorCode::UnterminatedString,
+            causes: format!(
+                "the string starting on line {} is still open at the end of the file, so no markers after it were read",
+                string_start.line
+            ),
+            source: None,
+            kind: UserErrorKind::Annotations,
+            file: path.into(),
+            span: Some(string_start),
+            help: Some("if this is not a string that spans lines, check `multiline_strings` for this language in VexDoc.toml".into()),
+        });
+    }
+
+    // Without `ENDVEXDOC`, the end of the file ends the last section too
+    if syntax.block_end != BlockEnd::Explicit && matches!(state, ParserState::Title | ParserState::Code) {
+        parsed.close_block(mem::take(&mut included));
+        state = ParserState::Ignore;
+    }
+
+    // A bare title marker has no title until its code is read
+    let title_text = if title_text.is_empty() { "(untitled)" } else { title_text };
+    // A block left open at the end of the file is either an error or kept as it stands
+    let unterminated = match state {
+        ParserState::Ignore => None,
+        ParserState::FileSummary => Some((ErrorCode::UnterminatedFileSummary, format!(
+            "the file summary starting on line {} is never closed with `{}`",
+            block_start.line,
+            syntax.summary_end_marker(style)
+        ))),
+        ParserState::Title | ParserState::ItemSummary => Some((ErrorCode::UnterminatedSection, format!(
+            "the summary of section '{}' starting on line {} is never closed, and the section never ends with `{}`",
+            title_text,
+            block_start.line,
+            syntax.end_marker()
+        ))),
+        ParserState::Code => Some((ErrorCode::UnterminatedSection, format!(
+            "section '{}' starting on line {} never ends with `{}`",
+            title_text,
+            block_start.line,
+            syntax.end_marker()
+        ))),
+    };
+    if let Some((code, causes)) = unterminated {
+        if !allow_unterminated {
+            parsed.errors.push(SubcommandError::UserError {
+                code,
+                causes,
+                source: None,
+                kind: UserErrorKind::Annotations,
+                file: path.into(),
+                span: Some(block_start),
+                help: None,
+            });
+        }
+        match state {
+            ParserState::FileSummary | ParserState::ItemSummary => {
+                parsed.add_summary(state, summary.finish(summary_start))
+            }
+            ParserState::Title => {
+                included.extend(pending.drain(..).skip_while(|l| l.trim().is_empty()));
+                parsed.add_code(included);
+            }
+            ParserState::Code => parsed.add_code(included),
+            ParserState::Ignore => {}
+        }
+    }
+    // A bare title marker takes its title from the code, so both need the whole section read
+    for section in &mut parsed.sections {
+        if section.internal.is_none() {
+            section.internal = section.summary.as_ref().and_then(Summary::internal);
+        }
+        let code = section.code.as_deref().unwrap_or_default();
+        section.signature = signature(code, syntax);
+        if section.title.is_empty() {
+            section.title = derived_title(code, section.signature.as_ref());
+        }
+    }
+    parsed
+}
+
+/// Splits an `[internal]` or `[public]` tag off the front of a title, as in `[internal] Retry policy`
+fn visibility_tag(title: &str) -> (Option<bool>, &str) {
+    let tag = title
+        .strip_prefix('[')
+        .and_then(|rest| rest.split_once(']'))
+        .and_then(|(tag, rest)| match tag.trim().to_lowercase().as_str() {
+            "internal" => Some((true, rest.trim())),
+            "public" => Some((false, rest.trim())),
+            _ => None,
+        });
+    match tag {
+        Some((internal, title)) => (Some(internal), title),
+        None => (None, title),
+    }
+}
+
+/// Points at the first non-blank character of a line, given its 0-based index
+fn marker_span(line_number: usize, line: &str) -> Span {
+    Span::new(line_number + 1, indentation(line).chars().count() + 1)
+}
+
+/// The leading whitespace of a line
+fn indentation(line: &str) -> &str {
+    &line[..line.len() - line.trim_start().len()]
+}
+
+/// Removes the indentation of the enclosing marker, so sections inside an `impl` or class body
+/// don't render shifted to the right
+fn dedent<'a>(line: &'a str, indent: &str) -> &'a str {
+    line.strip_prefix(indent).unwrap_or_else(|| line.trim_start())
+}
+
+/// Counts the brackets a line opens and closes, outside `"` strings, character literals and line
+/// comments
+fn brackets(line: &str, inline_comments: &str, open: &str, close: &str) -> (isize, isize) {
+    let (mut opened, mut closed) = (0, 0);
+    let mut in_string = false;
+    let mut chars = line.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' if in_string => {
+                chars.next();
+            }
+            '"' => in_string = !in_string,
+            _ if in_string => {}
+            _ if !inline_comments.is_empty() && line[i..].starts_with(inline_comments) => break,
+            // `'{'` is a character, not a bracket
+            _ if line[..i].ends_with('\'') && line[i + c.len_utf8()..].starts_with('\'') => {}
+            _ if open.contains(c) => opened += 1,
+            _ if close.contains(c) => closed += 1,
+            _ => {}
+        }
+    }
+    (opened, closed)
+}
+
+/// Strips a block comment delimiter and the marker keyword after it from the start of a line
+///
+/// Whitespace is allowed in between, as in `/* startsummary`.
+fn strip_marker<'a>(line: &'a str, delimiter: &str, keyword: &str) -> Option<&'a str> {
+    line.strip_prefix(delimiter)?.trim_start().strip_prefix(keyword)
+}
+
+/// Whether a line is an end-of-summary keyword followed by the given closing delimiter
+fn is_summary_end(line: &str, keyword: &str, close: &str) -> bool {
+    line.strip_prefix(keyword)
+        .is_some_and(|rest| rest.trim_start().starts_with(close))
+}
+
+/// Whether a line is an interpreter line such as `#!/usr/bin/env python` or `#! /bin/sh`
+pub(super) fn is_shebang(line: &str) -> bool {
+    line.strip_prefix("#!")
+        .is_some_and(|rest| rest.trim_start().starts_with('/'))
+}
@@ -0,0 +1,392 @@
+/*filesummary
+Comment syntaxes for common languages, used by `vexdoc init --preset` to write a working config.
+endsummary*/
+
+use crate::codes::ErrorCode;
+use crate::errors::{SubcommandError, UserErrorKind};
+use std::path::{Path, PathBuf};
+
+/// The comment syntax VexDoc should use for one language
+#[derive(Debug)]
+pub struct Preset {
+    /// Name used on the command line and as the `[languages.<name>]` table name
+    pub name: &'static str,
+    pub extensions: &'static [&'static str],
+    /// Whole file names, for files such as `Makefile` that have no extension to go by
+    pub filenames: &'static [&'static str],
+    pub inline_comments: &'static str,
+    /// Opening and closing block comment delimiters, in order of preference
+    pub multi_comments: &'static [(&'static str, &'static str)],
+    /// Whether block comments nest
+    pub nested_comments: bool,
+    /// Opening and closing delimiters of the string literals whose contents are never markers
+    pub multiline_strings: &'static [(&'static str, &'static str)],
+}
+
+const C_STYLE: &[(&str, &str)] = &[("/*", "*/")];
+const DOUBLE_QUOTES: &[(&str, &str)] = &[("\"", "\"")];
+const QUOTES: &[(&str, &str)] = &[("\"", "\""), ("'", "'")];
+/// Text blocks in Java, Kotlin, Swift and C#
+const TEXT_BLOCKS: &[(&str, &str)] = &[("\"\"\"", "\"\"\""), ("\"", "\"")];
+const TEMPLATES: &[(&str, &str)] = &[("`", "`"), ("\"", "\""), ("'", "'")];
+
+/// Directories written to `ignored_dirs` when nothing more specific is known
+pub const DEFAULT_IGNORED_DIRS: &[&str] = &["target", "node_modules", ".git"];
+
+pub const PRESETS: &[Preset] = &[
+    Preset {
+        name: "rust",
+        extensions: &["rs"],
+        filenames: &[],
+        inline_comments: "//",
+        multi_comments: C_STYLE,
+        nested_comments: true,
+        multiline_strings: &[("\"", "\""), ("r#\"", "\"#"), ("r##\"", "\"##")],
+    },
+    Preset {
+        name: "c",
+        extensions: &["c", "h"],
+        filenames: &[],
+        inline_comments: "//",
+        multi_comments: C_STYLE,
+        nested_comments: false,
+        multiline_strings: DOUBLE_QUOTES,
+    },
+    Preset {
+        name: "cpp",
+        extensions: &["cpp", "cc", "cxx", "hpp", "hh", "hxx"],
+        filenames: &[],
+        inline_comments: "//",
+        multi_comments: C_STYLE,
+        nested_comments: false,
+        multiline_strings: &[("\"", "\""), ("R\"(", ")\"")],
+    },
+    Preset {
+        name: "csharp",
+        extensions: &["cs"],
+        filenames: &[],
+        inline_comments: "//",
+        multi_comments: C_STYLE,
+        nested_comments: false,
+        multiline_strings: TEXT_BLOCKS,
+    },
+    Preset {
+        name: "java",
+        extensions: &["java"],
+        filenames: &[],
+        inline_comments: "//",
+        multi_comments: C_STYLE,
+        nested_comments: false,
+        multiline_strings: TEXT_BLOCKS,
+    },
+    Preset {
+        name: "kotlin",
+        extensions: &["kt", "kts"],
+        filenames: &[],
+        inline_comments: "//",
+        multi_comments: C_STYLE,
+        nested_comments: true,
+        multiline_strings: TEXT_BLOCKS,
+    },
+    Preset {
+        name: "swift",
+        extensions: &["swift"],
+        filenames: &[],
+        inline_comments: "//",
+        multi_comments: C_STYLE,
+        nested_comments: true,
+        multiline_strings: TEXT_BLOCKS,
+    },
+    Preset {
+        name: "go",
+        extensions: &["go"],
+        filenames: &[],
+        inline_comments: "//",
+        multi_comments: C_STYLE,
+        nested_comments: false,
+        multiline_strings: &[("`", "`"), ("\"", "\"")],
+    },
+    Preset {
+        name: "javascript",
+        extensions: &["js", "mjs", "cjs", "jsx"],
+        filenames: &[],
+        inline_comments: "//",
+        multi_comments: C_STYLE,
+        nested_comments: false,
+        multiline_strings: TEMPLATES,
+    },
+    Preset {
+        name: "typescript",
+        extensions: &["ts", "mts", "cts", "tsx"],
+        filenames: &[],
+        inline_comments: "//",
+        multi_comments: C_STYLE,
+        nested_comments: false,
+        multiline_strings: TEMPLATES,
+    },
+    Preset {
+        name: "python",
+        extensions: &["py", "pyi"],
+        filenames: &[],
+        inline_comments: "#",
+        multi_comments: &[("\"\"\"", "\"\"\""), ("'''", "'''")],
+        nested_comments: false,
+        multiline_strings: QUOTES,
+    },
+    Preset {
+        name: "ruby",
+        extensions: &["rb"],
+        filenames: &[],
+        inline_comments: "#",
+        multi_comments: &[("=begin", "=end")],
+        nested_comments: false,
+        multiline_strings: QUOTES,
+    },
+    // Shell, YAML, TOML and Makefiles have no block comments, so summaries are runs of line comments
+    Preset {
+        name: "shell",
+        extensions: &["sh", "bash", "zsh"],
+        filenames: &[],
+        inline_comments: "#",
+        multi_comments: &[],
+        nested_comments: false,
+        multiline_strings: QUOTES,
+    },
+    Preset {
+        name: "yaml",
+        extensions: &["yaml", "yml"],
+        filenames: &[],
+        inline_comments: "#",
+        multi_comments: &[],
+        nested_comments: false,
+        multiline_strings: &[],
+    },
+    Preset {
+        name: "toml",
+        extensions: &["toml"],
+        filenames: &[],
+        inline_comments: "#",
+        multi_comments: &[],
+        nested_comments: false,
+        multiline_strings: &[("\"\"\"", "\"\"\""), ("'''", "'''"), ("\"", "\""), ("'", "'")],
+    },
+    Preset {
+        name: "makefile",
+        extensions: &["mk"],
+        filenames: &["Makefile", "makefile", "GNUmakefile"],
+        inline_comments: "#",
+        multi_comments: &[],
+        nested_comments: false,
+        multiline_strings: &[],
+    },
+    Preset {
+        name: "lua",
+        extensions: &["lua"],
+        filenames: &[],
+        inline_comments: "--",
+        multi_comments: &[("--[[", "]]"), ("--[=[", "]=]"), ("--[==[", "]==]")],
+        nested_comments: false,
+        multiline_strings: &[("[[", "]]"), ("\"", "\""), ("'", "'")],
+    },
+    Preset {
+        name: "sql",
+        extensions: &["sql"],
+        filenames: &[],
+        inline_comments: "--",
+        multi_comments: C_STYLE,
+        nested_comments: false,
+        multiline_strings: &[("'", "'")],
+    },
+    Preset {
+        name: "haskell",
+        extensions: &["hs"],
+        filenames: &[],
+        inline_comments: "--",
+        multi_comments: &[("{-", "-}")],
+        nested_comments: true,
+        multiline_strings: DOUBLE_QUOTES,
+    },
+    // OCaml, HTML, XML and CSS have no line comments, so titles and end markers are block comments
+    Preset {
+        name: "ocaml",
+        extensions: &["ml", "mli"],
+        filenames: &[],
+        inline_comments: "",
+        multi_comments: &[("(*", "*)")],
+        nested_comments: true,
+        multiline_strings: &[("{|", "|}"), ("\"", "\"")],
+    },
+    Preset {
+        name: "html",
+        extensions: &["html", "htm"],
+        filenames: &[],
+        inline_comments: "",
+        multi_comments: &[("<!--", "-->")],
+        nested_comments: false,
+        multiline_strings: &[],
+    },
+    Preset {
+        name: "xml",
+        extensions: &["xml", "svg"],
+        filenames: &[],
+        inline_comments: "",
+        multi_comments: &[("<!--", "-->")],
+        nested_comments: false,
+        multiline_strings: &[],
+    },
+    Preset {
+        name: "css",
+        extensions: &["css"],
+        filenames: &[],
+        inline_comments: "",
+        multi_comments: C_STYLE,
+        nested_comments: false,
+        multiline_strings: QUOTES,
+    },
+];
+
+/// Looks up a preset by its name or by one of its file extensions
+pub fn find_preset(name: &str) -> Option<&'static Preset> {
+    let name = name.trim().to_lowercase();
+    PRESETS
+        .iter()
+        .find(|p| p.name == name)
+        .or_else(|| PRESETS.iter().find(|p| p.extensions.contains(&name.as_str())))
+}
+
+/// Finds the preset that documents a file, by its whole name first and then by its extension
+pub fn preset_for_file(path: &Path) -> Option<&'static Preset> {
+    let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+    let ext = path.extension().and_then(|ext| ext.to_str()).unwrap_or_default();
+    PRESETS
+        .iter()
+        .find(|p| p.filenames.contains(&name))
+        .or_else(|| PRESETS.iter().find(|p| p.extensions.contains(&ext)))
+}
+
+/// Resolves a comma-separated preset list such as `rust,python`
+pub fn parse_presets(list: &str) -> Result<Vec<&'static Preset>, SubcommandError> {
+    let mut presets: Vec<&'static Preset> = Vec::new();
+    let mut unknown = Vec::new();
+    for name in list.split(',').filter(|n| !n.trim().is_empty()) {
+        match find_preset(name) {
+            // Asking for `c,h` should not write the C table twice
+            Some(preset) if presets.iter().any(|p| p.name == preset.name) => (),
+            Some(preset) => presets.push(preset),
+            None => unknown.push(name.trim()),
+        }
+    }
+
+    if !unknown.is_empty() {
+        let available: Vec<&str> = PRESETS.iter().map(|p| p.name).collect();
+        return Err(SubcommandError::UserError {
+            code: ErrorCode::UnknownPreset,
+            causes: format!("unknown preset(s): {}", unknown.join(", ")),
+            source: None,
+            kind: UserErrorKind::Config,
+            file: PathBuf::new(),
+            span: None,
+            help: Some(format!("available presets: {}", available.join(", "))),
+        });
+    }
+    Ok(presets)
+}
+
+/// Quotes a string for TOML, picking literal quotes when the value contains `"`
+fn toml_string(value: &str) -> String {
+    toml::Value::String(value.to_string()).to_string()
+}
+
+fn toml_array(values: &[&str]) -> String {
+    let items: Vec<String> = values.iter().map(|v| toml_string(v)).collect();
+    format!("[{}]", items.join(", "))
+}
+
+/// Writes a single pair the familiar way, `["/*", "*/"]`, and several as a list of pairs
+fn toml_pairs(pairs: &[(&str, &str)]) -> String {
+    match pairs {
+        [(open, close)] => toml_array(&[open, close]),
+        _ => {
+            let items: Vec<String> = pairs
+                .iter()
+                .map(|(open, close)| toml_array(&[open, close]))
+                .collect();
+            format!("[{}]", items.join(", "))
+        }
+    }
+}
+
+/// Renders a commented VexDoc.toml with one `[languages.*]` table per preset
+///
+/// Without presets, the top-level settings document C-style sources instead, so the file is valid
+/// from the start.
+pub fn render_config(presets: &[&Preset], ignored_dirs: &[&str]) -> String {
+    let mut content = format!(
+        "# VexDoc configuration\n\
+         \n\
+         # Directories to skip. They are matched by name anywhere in the file tree.\n\
+         ignored_dirs = {}\n",
+        toml_array(ignored_dirs)
+    );
+    if presets.is_empty() {
+        content.push_str(
+            "\n\
+             # No languages were chosen, so files with these extensions (without the leading\n\
+             # period) are documented with the C-style syntax below. Run\n\
+             # `vexdoc init --preset <languages> --force` or `--detect --force` to start from\n\
+             # the built-in syntax of your languages instead.\n\
+             file_extensions = [\"c\", \"h\", \"cpp\", \"hpp\", \"rs\", \"go\", \"java\", \"js\", \"ts\"]\n\
+             # Starts titles (followed by `!`) and `ENDVEXDOC` markers\n\
+             inline_comments = \"//\"\n\
+             # Opening and closing delimiters around summaries\n\
+             multi_comments = [\"/*\", \"*/\"]\n\
+             # String literals that can span lines, whose contents are never read as markers\n\
+             multiline_strings = [\"\\\"\", \"\\\"\"]\n",
+        );
+    } else {
+        content.push_str(
+            "\n\
+             # Extensions (without the leading period) documented with the top-level\n\
+             # inline_comments/multi_comments. Extensions listed in a [languages.*] table\n\
+             # below are documented automatically and do not need to be repeated here.\n\
+             file_extensions = []\n",
+        );
+    }
+
+    for preset in presets {
+        content.push_str(&format!(
+            "\n[languages.{}]\nextensions = {}\n",
+            preset.name,
+            toml_array(preset.extensions)
+        ));
+        if !preset.filenames.is_empty() {
+            content.push_str(&format!("filenames = {}\n", toml_array(preset.filenames)));
+        }
+        if preset.inline_comments.is_empty() {
+            content.push_str("# No line comments: titles and `ENDVEXDOC` markers are written as block comments\n");
+        } else {
+            content.push_str(&format!(
+                "# Starts titles (followed by `!`) and `ENDVEXDOC` markers\ninline_comments = {}\n",
+                toml_string(preset.inline_comments)
+            ));
+        }
+        if preset.multi_comments.is_empty() {
+            content.push_str("# No block comments: summaries are written as runs of line comments\n");
+        } else {
+            content.push_str(&format!(
+                "# Opening and closing delimiters around summaries\nmulti_comments = {}\n",
+                toml_pairs(preset.multi_comments)
+            ));
+        }
+        if !preset.multiline_strings.is_empty() {
+            content.push_str(&format!(
+                "# String literals that can span lines, whose contents are never read as markers\nmultiline_strings = {}\n",
+                toml_pairs(preset.multiline_strings)
+            ));
+        }
+        if preset.nested_comments {
+            content.push_str("# Block comments nest, so inner comments are matched in pairs\nnested_comments = true\n");
+        }
+    }
+    content
+}
@@ -0,0 +1,183 @@
+/*filesummary
+Finds the declaration a section documents, such as `pub fn parse(input: &str) -> Ast`, to show it under the title and to name sections written with a bare title marker.
+These are heuristics built on keywords like `fn`, `def`, `class`, `struct` and `function`, plus the `type name(` shape of C-like languages, not a parser for any one language.
+endsummary*/
+
+use super::parser::CommentSyntax;
+
+/// Keywords that start a declaration, across the languages VexDoc is used with
+const KEYWORDS: &[&str] = &[
+    "fn", "def", "class", "struct", "enum", "union", "trait", "impl", "interface", "type", "typedef",
+    "function", "func", "fun", "sub", "proc", "mod", "module", "namespace", "macro_rules!", "const",
+    "static", "let", "var", "val", "object", "record", "protocol", "extension",
+];
+
+/// Words that may come before the keyword of a declaration
+const MODIFIERS: &[&str] = &[
+    "pub", "pub(crate)", "pub(super)", "export", "default", "async", "unsafe", "extern", "public",
+    "private", "protected", "internal", "abstract", "final", "sealed", "open", "data", "inline",
+    "virtual", "override", "local", "declare", "readonly", "mut", "ref",
+];
+
+/// Words that start a statement, so `if (x)` is never read as a C-like declaration
+const STATEMENTS: &[&str] = &[
+    "if", "for", "while", "switch", "match", "return", "else", "catch", "new", "do", "throw",
+    "print", "await", "yield", "delete", "case",
+];
+
+/// A declaration found at the top of a section's code
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Signature<'a> {
+    /// The declaration up to its body, with a signature split over several lines joined into one
+    pub(super) text: String,
+    /// The name it declares, when there is one
+    pub(super) name: Option<&'a str>,
+}
+
+/// Reads the declaration a section's code starts with, after any comments, attributes and
+/// decorators
+///
+/// Code that starts with anything else has no signature, even if a declaration comes later.
+pub(super) fn signature<'a>(code: &[&'a str], syntax: &CommentSyntax) -> Option<Signature<'a>> {
+    let start = code.iter().position(|line| {
+        let line = line.trim();
+        !line.is_empty() && !syntax.is_comment(line) && !is_attribute(line)
+    })?;
+    let first = code[start].trim();
+    declared_name(first)?;
+    // Parameters split over several lines are joined until their brackets close
+    let mut text = first.to_string();
+    for line in code[start + 1..].iter().take(8) {
+        if depth(&text) <= 0 || text.contains('{') {
+            break;
+        }
+        text.push(' ');
+        text.push_str(line.trim());
+    }
+    let text = text.replace("( ", "(").replace(", )", ")").replace(" )", ")");
+    Some(Signature {
+        text: cut(&text).to_string(),
+        name: declared_name(first).flatten(),
+    })
+}
+
+/// The title of a section written with a bare title marker: the name its code declares, or else
+/// its first non-blank line
+pub(super) fn derived_title<'a>(code: &[&'a str], signature: Option<&Signature<'a>>) -> &'a str {
+    if let Some(name) = signature.and_then(|s| s.name) {
+        return name;
+    }
+    code.iter()
+        .map(|line| cut(line.trim()))
+        .find(|line| !line.is_empty())
+        .unwrap_or_default()
+}
+
+/// Lines such as `#[derive(Debug)]`, `@property` and `[Serializable]` that come before a declaration
+pub(super) fn is_attribute(line: &str) -> bool {
+    line.starts_with("#[") || line.starts_with("#![") || line.starts_with('@') || line.starts_with('[')
+}
+
+/// Whether a line is a declaration, and if so the name it declares
+///
+/// `Some(None)` is a declaration without a name of its own, such as a Go method with an odd receiver.
+fn declared_name(line: &str) -> Option<Option<&str>> {
+    let mut rest = line;
+    let mut keyword = None;
+    while let Some((word, after)) = split_word(rest) {
+        let bare = word.split(['<', '(']).next().unwrap_or(word);
+        if KEYWORDS.contains(&bare) && !word.contains('(') {
+            keyword = Some(bare);
+        } else if !MODIFIERS.contains(&word) {
+            break;
+        }
+        rest = after;
+    }
+    match keyword {
+        // Everything after `impl` names it, as in `impl Display for Point`
+        Some("impl") => Some(Some(cut(rest.trim())).filter(|name| !name.is_empty())),
+        // In C, `static` and `const` come before a type: `static int parse(const char *input)`
+        Some("static" | "const") if c_like_name(line).is_some() => Some(c_like_name(line)),
+        Some(_) => Some(identifier(skip_receiver(rest.trim_start()))),
+        None => c_like_name(line).map(Some),
+    }
+}
+
+/// Splits the first whitespace-separated word off a line
+fn split_word(line: &str) -> Option<(&str, &str)> {
+    let line = line.trim_start();
+    if line.is_empty() {
+        return None;
+    }
+    Some(line.split_once(char::is_whitespace).unwrap_or((line, "")))
+}
+
+/// Skips the `(r *Reader)` receiver of a Go method
+fn skip_receiver(rest: &str) -> &str {
+    if !rest.starts_with('(') {
+        return rest;
+    }
+    match rest.find(')') {
+        Some(end) => rest[end + 1..].trim_start(),
+        None => rest,
+    }
+}
+
+/// The identifier at the start of some text, if any
+fn identifier(text: &str) -> Option<&str> {
+    let end = text
+        .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+        .unwrap_or(text.len());
+    Some(&text[..end]).filter(|name| !name.is_empty())
+}
+
+/// The name in a C-like declaration such as `static int parse(const char *input)`
+///
+/// Everything before the `(` must be words, with at least a type and a name.
+fn c_like_name(line: &str) -> Option<&str> {
+    let before = &line[..line.find('(')?];
+    let words: Vec<&str> = before.split_whitespace().collect();
+    let typed = words.len() >= 2
+        && !STATEMENTS.contains(&words[0])
+        && words.iter().all(|w| {
+            w.chars().all(|c| c.is_alphanumeric() || "_*&:<>,[]~".contains(c))
+        });
+    if !typed {
+        return None;
+    }
+    identifier(words.last()?.trim_start_matches(['*', '&']))
+}
+
+/// How many more brackets a line opens than it closes
+fn depth(text: &str) -> isize {
+    text.chars()
+        .map(|c| match c {
+            '(' | '[' => 1,
+            ')' | ']' => -1,
+            _ => 0,
+        })
+        .sum()
+}
+
+/// Cuts a declaration before its body: at the first `{` outside brackets, at a `:` that ends the
+/// line, or at a final `;`
+fn cut(text: &str) -> &str {
+    let mut depth = 0;
+    let brace = text.char_indices().find(|&(_, c)| {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            _ => {}
+        }
+        c == '{' && depth <= 0
+    });
+    let text = match brace {
+        Some((brace, _)) => &text[..brace],
+        None => text,
+    };
+    let text = text.trim_end();
+    text.strip_suffix(':')
+        .or_else(|| text.strip_suffix(';'))
+        .unwrap_or(text)
+        .trim_end()
+}
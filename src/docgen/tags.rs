@@ -0,0 +1,169 @@
+/*filesummary
+Structured fields inside summaries, written as `@param name description`, `@returns`, `@errors`, `@example`, `@see`, `@since`, `@deprecated`, `@internal` and `@public`.
+A tag runs from its line up to the next tag, so tags are written after the description, as in Javadoc.
+endsummary*/
+
+use super::links::Links;
+use super::parser::Summary;
+use super::{escape_html, markdown_html, summary_html, SummaryFormat};
+use crate::errors::Span;
+
+/// The tags VexDoc knows how to render
+///
+/// `@internal` and `@public` say who a section is for, and are not shown.
+pub(super) const KNOWN_TAGS: &[&str] = &[
+    "param", "returns", "errors", "example", "see", "since", "deprecated", "internal", "public",
+];
+
+/// One `@name` tag in a summary
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Tag<'a> {
+    pub(super) name: &'a str,
+    /// The text after the name, then every line up to the next tag
+    pub(super) lines: Vec<&'a str>,
+    /// Where the `@` is
+    pub(super) span: Span,
+}
+
+impl Tag<'_> {
+    /// The tag's text as one paragraph, e.g. the description of a `@returns`
+    fn text(&self) -> String {
+        let words: Vec<&str> = self.lines.iter().map(|l| l.trim()).filter(|l| !l.is_empty()).collect();
+        words.join(" ")
+    }
+}
+
+/// Collects the lines of a summary as they are read, splitting off its tags
+#[derive(Debug, Default)]
+pub(super) struct SummaryBuilder<'a> {
+    lines: Vec<&'a str>,
+    tags: Vec<Tag<'a>>,
+    /// Whether the last line was inside a fenced code block, where `@` never starts a tag
+    in_fence: bool,
+}
+
+impl<'a> SummaryBuilder<'a> {
+    /// Adds a line of the summary, given its line number and the line as it is in the file
+    pub(super) fn push(&mut self, text: &'a str, line_number: usize, line: &str) {
+        let trimmed = text.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            self.in_fence = !self.in_fence;
+        }
+        // Inside an example only known tags end it, so decorators such as `@property` are kept
+        let in_example = self.tags.last().is_some_and(|t| t.name == "example");
+        let tag = trimmed
+            .strip_prefix('@')
+            .filter(|_| !self.in_fence)
+            .map(|rest| rest.split_once(char::is_whitespace).unwrap_or((rest, "")))
+            .filter(|(name, _)| !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'))
+            .filter(|(name, _)| !in_example || KNOWN_TAGS.contains(name));
+        match (tag, self.tags.last_mut()) {
+            (Some((name, rest)), _) => {
+                let column = line.len() - line.trim_start().len() + line.trim_start().find('@').unwrap_or(0);
+                self.tags.push(Tag {
+                    name,
+                    lines: vec![rest],
+                    span: Span::new(line_number + 1, line[..column].chars().count() + 1),
+                });
+            }
+            (None, Some(tag)) => tag.lines.push(text),
+            (None, None) => self.lines.push(text),
+        }
+    }
+
+    /// Finishes the summary, leaving the builder empty for the next one
+    pub(super) fn finish(&mut self, span: Span) -> Summary<'a> {
+        let builder = std::mem::take(self);
+        Summary {
+            lines: builder.lines,
+            tags: builder.tags,
+            span,
+        }
+    }
+}
+
+/// Renders a summary: a deprecation banner, the description, then a block for each kind of tag
+pub(super) fn render_summary(summary: &Summary, format: SummaryFormat, links: &Links) -> String {
+    let inline = |text: &str| match format {
+        SummaryFormat::Markdown => inline_markdown(text, links),
+        SummaryFormat::Plain => links.plain(text, escape_html),
+    };
+    let text = |tag: &Tag| inline(&tag.text());
+    let named = |name: &'static str| summary.tags.iter().filter(move |t| t.name == name);
+    let mut html = String::new();
+
+    for tag in named("deprecated") {
+        let reason = text(tag);
+        let reason = if reason.is_empty() { String::new() } else { format!(" {}", reason) };
+        html.push_str(&format!("<div class=\"deprecated\"><strong>Deprecated.</strong>{}</div>", reason));
+    }
+    if summary.lines.iter().any(|l| !l.trim().is_empty()) || summary.tags.is_empty() {
+        html.push_str(&summary_html(&summary.lines, format, links));
+    }
+
+    let params: Vec<String> = named("param")
+        .map(|tag| {
+            let full = tag.text();
+            let (name, description) = full.split_once(' ').unwrap_or((&full, ""));
+            format!("<tr><td><code>{}</code></td><td>{}</td></tr>", escape_html(name), inline(description))
+        })
+        .collect();
+    if !params.is_empty() {
+        html.push_str(&format!(
+            "<table class=\"params\"><thead><tr><th>Parameter</th><th>Description</th></tr></thead><tbody>{}</tbody></table>",
+            params.concat()
+        ));
+    }
+    for (name, heading) in [("returns", "Returns"), ("errors", "Errors")] {
+        for tag in named(name) {
+            html.push_str(&format!("<div class=\"tag {}\"><h4>{}</h4><p>{}</p></div>", name, heading, text(tag)));
+        }
+    }
+    for tag in named("example") {
+        html.push_str(&format!("<div class=\"tag example\"><h4>Example</h4>{}</div>", example(tag, format, links)));
+    }
+    let see: Vec<String> = named("see").map(text).collect();
+    if !see.is_empty() {
+        html.push_str(&format!("<p class=\"tag see\"><strong>See also:</strong> {}</p>", see.join(", ")));
+    }
+    for tag in named("since") {
+        html.push_str(&format!("<p class=\"tag since\"><strong>Since:</strong> {}</p>", text(tag)));
+    }
+    // Unknown tags are kept as they were written, so nothing is lost when one is misspelt
+    for tag in summary.tags.iter().filter(|t| !KNOWN_TAGS.contains(&t.name)) {
+        html.push_str(&format!("<p class=\"tag\"><code>@{}</code> {}</p>", escape_html(tag.name), text(tag)));
+    }
+    html
+}
+
+/// Renders an example as code, or as Markdown when it brings its own fenced block
+fn example(tag: &Tag, format: SummaryFormat, links: &Links) -> String {
+    let lines: Vec<&str> = tag
+        .lines
+        .iter()
+        .copied()
+        .skip_while(|l| l.trim().is_empty())
+        .collect();
+    let fenced = lines.first().is_some_and(|l| l.trim_start().starts_with("```"));
+    if fenced && format == SummaryFormat::Markdown {
+        return markdown_html(&lines.join("\n"), links);
+    }
+    let indent = lines
+        .iter()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| l.len() - l.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let code: Vec<&str> = lines.iter().map(|l| l.get(indent..).unwrap_or(l.trim_start())).collect();
+    format!("<pre><code>{}</code></pre>", escape_html(code.join("\n").trim_end()))
+}
+
+/// Renders Markdown without the paragraph around it, for text inside a table cell or a line
+fn inline_markdown(text: &str, links: &Links) -> String {
+    let html = markdown_html(text, links);
+    let html = html.trim_end();
+    match html.strip_prefix("<p>").and_then(|h| h.strip_suffix("</p>")) {
+        Some(inner) if !inner.contains("<p>") => inner.to_string(),
+        _ => html.to_string(),
+    }
+}
@@ -1,5 +1,4 @@
-//! Test suite for the docgen module
-/*startsummary
+/*filesummary
 These tests make sure our documentation generation actually works.
 I've tried to cover the main happy paths and a few edge cases,
 but if you find bugs, feel free to add more tests!
@@ -8,9 +7,16 @@ endsummary*/
 use std::{env, error::Error};
 
 use super::*;
+use super::parser::is_shebang;
 use assert_fs::fixture::TempDir;
 use rand::Rng;
 
+/// Writes the page for one file, the way `document` does for each file in a project
+fn create_doc(path: &Path, conf: &DocGenConfig, docs_dir: &Path) -> Result<FileCheck, SubcommandError> {
+    let mut results = generate(conf, &[path], docs_dir, &ProgressBar::hidden(), false);
+    results.remove(0)
+}
+
 /// Generates a random alphanumeric string of the specified length
 fn generate_random_string(rng: &mut impl Rng, len: usize) -> String {
     const CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
@@ -74,12 +80,22 @@ fn rand_dir_entries(path: &Path) -> Vec<PathBuf> {
     paths
 }
 
+/// Reads the single HTML page written into `dir`
+fn read_only_page(dir: &Path) -> io::Result<String> {
+    let page = fs::read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .find(|e| e.path().extension().is_some_and(|ext| ext == "html"))
+        .expect("page was written");
+    fs::read_to_string(page.path())
+}
+
 #[test]
 fn random_get_all_files() -> Result<(), Box<dyn Error>> {
     let temporary_dir = TempDir::new()?;
     let mut test_files = rand_dir_entries(temporary_dir.path());
 
-    let mut files = DocGenConfig::get_files_helper(temporary_dir.path().into(), &vec![])?;
+    let filter = WalkFilter::new(temporary_dir.path(), &[]);
+    let mut files = DocGenConfig::get_files_helper(temporary_dir.path().into(), &filter)?;
 
     test_files.sort();
     files.sort();
@@ -110,9 +126,9 @@ file_extensions = ["c", "h"]
     let conf = DocGenConfig::read_config().expect("Should be able to read config");
 
     assert!(
-        conf.inline_comments == "//".to_string()
-            && conf.multi_comments == vec!["/*".to_string(), "*/".to_string()]
-            && conf.ignored_dirs.len() == 0
+        conf.inline_comments == "//"
+            && conf.multi_comments == vec![DelimiterPair::new("/*", "*/")]
+            && conf.ignored_dirs.is_empty()
             && conf.file_extensions == vec!["c".to_string(), "h".to_string()]
     );
 
@@ -167,10 +183,13 @@ file_extensions = []
     dbg!(&conf);
 
     if let SubcommandError::UserError {
+        code: _,
         causes: _,
         source: _,
         kind: _,
         file: _,
+        span: _,
+        help: _,
     } = conf.unwrap_err()
     {
         // Restore original directory
@@ -397,3 +416,1510 @@ file_extensions = ["rs"]
     env::set_current_dir(original_dir)?;
     Ok(())
 }
+
+#[test]
+fn language_profiles_select_syntax_per_file() -> Result<(), Box<dyn Error>> {
+    let conf = DocGenConfig::parse_config(
+        r##"inline_comments = "//"
+multi_comments = ["/*", "*/"]
+ignored_dirs = []
+file_extensions = ["rs"]
+
+[languages.py]
+inline_comments = "#"
+multi_comments = ['"""', '"""']
+
+[languages.c]
+extensions = ["c", "h"]
+inline_comments = "//"
+multi_comments = ["/*", "*/"]
+"##,
+        Path::new("VexDoc.toml"),
+    )?;
+
+    let python = conf.syntax_for(Path::new("src/main.py")).expect("py has a profile");
+    assert_eq!(python.inline_comments, "#");
+    assert_eq!(python.multi_comments, [DelimiterPair::new("\"\"\"", "\"\"\"")]);
+
+    let header = conf.syntax_for(Path::new("include/lib.h")).expect("h has a profile");
+    assert_eq!(header.inline_comments, "//");
+
+    // Anything without a profile falls back to the top-level syntax
+    let rust = conf.syntax_for(Path::new("src/lib.rs")).expect("rs uses the default");
+    assert_eq!(rust.inline_comments, "//");
+    assert_eq!(rust.multi_comments, [DelimiterPair::new("/*", "*/")]);
+    Ok(())
+}
+
+#[test]
+fn mixed_language_generation() -> Result<(), Box<dyn Error>> {
+    let tmp_dir = TempDir::new()?;
+    let docs_dir = tmp_dir.path().join("docs");
+    fs::create_dir(&docs_dir)?;
+
+    // No top-level syntax at all: every extension is covered by a profile
+    let conf = DocGenConfig::parse_config(
+        r##"ignored_dirs = []
+file_extensions = []
+
+[languages.rs]
+inline_comments = "//"
+multi_comments = ["/*", "*/"]
+
+[languages.py]
+inline_comments = "#"
+multi_comments = ['"""', '"""']
+"##,
+        Path::new("VexDoc.toml"),
+    )?;
+
+    let rust_file = tmp_dir.path().join("lib.rs");
+    fs::write(
+        &rust_file,
+        "//! Rust Function\n/*startsummary\nDocumented in Rust.\nendsummary*/\nfn f() {}\n// ENDVEXDOC\n",
+    )?;
+    let python_file = tmp_dir.path().join("tool.py");
+    fs::write(
+        &python_file,
+        "#! Python Function\n\"\"\"startsummary\nDocumented in Python.\nendsummary\"\"\"\ndef f(): pass\n# ENDVEXDOC\n",
+    )?;
+
+    assert!(create_doc(&rust_file, &conf, &docs_dir)?.has_vexdoc);
+    assert!(create_doc(&python_file, &conf, &docs_dir)?.has_vexdoc);
+
+    let pages: Vec<String> = fs::read_dir(&docs_dir)?
+        .map(|entry| fs::read_to_string(entry?.path()))
+        .collect::<io::Result<_>>()?;
+    assert!(pages.iter().any(|p| p.contains("Documented in Rust.")));
+    assert!(pages.iter().any(|p| p.contains("Documented in Python.")));
+    Ok(())
+}
+
+#[test]
+fn deny_extension_in_two_profiles() {
+    let conf = DocGenConfig::parse_config(
+        r##"ignored_dirs = []
+file_extensions = []
+
+[languages.c]
+extensions = ["c", "h"]
+inline_comments = "//"
+multi_comments = ["/*", "*/"]
+
+[languages.cpp]
+extensions = ["cpp", "h"]
+inline_comments = "//"
+multi_comments = ["/*", "*/"]
+"##,
+        Path::new("VexDoc.toml"),
+    );
+
+    match conf {
+        Err(SubcommandError::UserError { causes, .. }) => {
+            assert!(causes.contains("Extension 'h' is listed in more than one language profile"));
+        }
+        other => panic!("Expected a config error, got {:?}", other),
+    }
+}
+
+#[test]
+fn every_preset_writes_a_valid_config() -> Result<(), Box<dyn Error>> {
+    for preset in presets::PRESETS {
+        let config = presets::render_config(&[preset], &[]);
+        let conf = DocGenConfig::parse_config(&config, Path::new("VexDoc.toml"))
+            .unwrap_or_else(|e| panic!("preset {} produced an invalid config: {}", preset.name, e));
+        let sample = PathBuf::from(format!("sample.{}", preset.extensions[0]));
+        let syntax = conf.syntax_for(&sample).expect("preset covers its own extensions");
+        assert_eq!(syntax.inline_comments, preset.inline_comments);
+        let pairs: Vec<DelimiterPair> = preset
+            .multi_comments
+            .iter()
+            .map(|(open, close)| DelimiterPair::new(open, close))
+            .collect();
+        assert_eq!(syntax.multi_comments, pairs);
+        assert_eq!(syntax.nested_comments, preset.nested_comments);
+    }
+
+    let all: Vec<&presets::Preset> = presets::PRESETS.iter().collect();
+    let config = presets::render_config(&all, presets::DEFAULT_IGNORED_DIRS);
+    DocGenConfig::parse_config(&config, Path::new("VexDoc.toml"))?;
+    Ok(())
+}
+
+#[test]
+fn preset_lookup() {
+    let found = presets::parse_presets("rust, py,h,c").expect("all presets exist");
+    let names: Vec<&str> = found.iter().map(|p| p.name).collect();
+    assert_eq!(names, ["rust", "python", "c"]);
+
+    match presets::parse_presets("rust,brainfuck") {
+        Err(SubcommandError::UserError { causes, .. }) => assert!(causes.contains("brainfuck")),
+        other => panic!("Expected an unknown preset error, got {:?}", other),
+    }
+}
+
+#[test]
+fn init_only_overwrites_with_force() -> Result<(), Box<dyn Error>> {
+    let tmp_dir = TempDir::new()?;
+    let config = tmp_dir.path().join("VexDoc.toml");
+    let rust = presets::find_preset("rust").expect("rust preset exists");
+
+    // Without presets the config documents C-style sources, and is valid as written
+    DocGenConfig::create_config(tmp_dir.path().into(), &[], &[], false)?;
+    let conf = DocGenConfig::read_config_at(&config)?;
+    assert!(conf.syntax_for(Path::new("main.c")).is_some());
+    let err = DocGenConfig::create_config(tmp_dir.path().into(), &[rust], presets::DEFAULT_IGNORED_DIRS, false).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+
+    DocGenConfig::create_config(tmp_dir.path().into(), &[rust], presets::DEFAULT_IGNORED_DIRS, true)?;
+    let conf = DocGenConfig::read_config_at(&config)?;
+    assert!(conf.syntax_for(Path::new("main.rs")).is_some());
+    Ok(())
+}
+
+#[test]
+fn detect_languages_and_build_dirs() -> Result<(), Box<dyn Error>> {
+    let tmp_dir = TempDir::new()?;
+    let root = tmp_dir.path();
+    fs::create_dir_all(root.join("src/nested"))?;
+    fs::create_dir_all(root.join("scripts"))?;
+    fs::create_dir_all(root.join("target/debug"))?;
+    fs::create_dir_all(root.join("web/node_modules/pkg"))?;
+    fs::write(root.join("src/lib.rs"), "")?;
+    fs::write(root.join("src/nested/mod.rs"), "")?;
+    fs::write(root.join("scripts/tool.py"), "")?;
+    fs::write(root.join("README.md"), "")?;
+    // Makefiles are recognised by name, having no extension
+    fs::write(root.join("Makefile"), "")?;
+    // Files inside ignored directories must not count towards the histogram
+    fs::write(root.join("target/debug/build.py"), "")?;
+    fs::write(root.join("web/node_modules/pkg/index.js"), "")?;
+
+    let detection = detect::detect(root)?;
+    let languages: Vec<(&str, usize)> = detection
+        .languages
+        .iter()
+        .map(|(preset, count)| (preset.name, *count))
+        .collect();
+    assert_eq!(languages, [("rust", 2), ("makefile", 1), ("python", 1)]);
+    assert_eq!(detection.ignored_dirs, ["target", "node_modules"]);
+
+    let found: Vec<&presets::Preset> = detection.languages.iter().map(|(p, _)| *p).collect();
+    let config = presets::render_config(&found, &detection.ignored_dirs);
+    let conf = DocGenConfig::parse_config(&config, Path::new("VexDoc.toml"))?;
+    assert_eq!(conf.ignored_dirs, [PathBuf::from("target"), PathBuf::from("node_modules")]);
+    assert!(conf.syntax_for(Path::new("Makefile")).is_some_and(|syntax| syntax.inline_comments == "#"));
+    Ok(())
+}
+
+#[test]
+fn finds_config_in_parent_directory() -> Result<(), Box<dyn Error>> {
+    let tmp_dir = TempDir::new()?;
+    let nested = tmp_dir.path().join("src").join("deeply").join("nested");
+    fs::create_dir_all(&nested)?;
+    fs::write(
+        tmp_dir.path().join("VexDoc.toml"),
+        r#"inline_comments = "//"
+multi_comments = ["/*", "*/"]
+ignored_dirs = []
+file_extensions = ["rs"]
+"#,
+    )?;
+
+    let found = DocGenConfig::find_config(&nested).expect("config is in an ancestor");
+    assert_eq!(found, tmp_dir.path().join("VexDoc.toml"));
+    Ok(())
+}
+
+#[test]
+fn config_directory_is_project_root() -> Result<(), Box<dyn Error>> {
+    let tmp_dir = TempDir::new()?;
+    let project = tmp_dir.path().join("project");
+    fs::create_dir_all(project.join("src"))?;
+    fs::write(
+        project.join("VexDoc.toml"),
+        r#"inline_comments = "//"
+multi_comments = ["/*", "*/"]
+ignored_dirs = []
+file_extensions = ["rs"]
+"#,
+    )?;
+    fs::write(
+        project.join("src").join("lib.rs"),
+        "//! Root Relative\n/*startsummary\nNamed from the project root.\nendsummary*/\nfn f() {}\n// ENDVEXDOC\n",
+    )?;
+    // Files next to the project, but outside it, must not be picked up
+    fs::write(tmp_dir.path().join("outside.rs"), "//! Outside\n")?;
+
+    let conf = DocGenConfig::read_config_at(&project.join("VexDoc.toml"))?;
+    let files = conf.get_files()?;
+    assert_eq!(files, [project.join("src").join("lib.rs")]);
+
+    document(conf, files, false, true)?;
+    let page = project.join("docs").join("src_lib-rs.html");
+    assert!(fs::read_to_string(page)?.contains("Named from the project root."));
+    Ok(())
+}
+
+#[test]
+fn source_roots_and_output_dir() -> Result<(), Box<dyn Error>> {
+    let tmp_dir = TempDir::new()?;
+    let root = tmp_dir.path();
+    fs::create_dir_all(root.join("src"))?;
+    fs::create_dir_all(root.join("lib"))?;
+    fs::create_dir_all(root.join("scripts"))?;
+    fs::write(root.join("src").join("main.rs"), "")?;
+    fs::write(root.join("lib").join("util.rs"), "")?;
+    fs::write(root.join("scripts").join("fixture.rs"), "")?;
+    fs::write(
+        root.join("VexDoc.toml"),
+        r#"inline_comments = "//"
+multi_comments = ["/*", "*/"]
+ignored_dirs = []
+file_extensions = ["rs"]
+source_roots = ["src", "lib", "src"]
+output_dir = "target/vexdoc"
+"#,
+    )?;
+
+    let conf = DocGenConfig::read_config_at(&root.join("VexDoc.toml"))?;
+    let files = conf.get_files()?;
+    assert_eq!(files, [root.join("lib").join("util.rs"), root.join("src").join("main.rs")]);
+
+    document(conf, files, false, true)?;
+    assert!(root.join("target/vexdoc/src_main-rs.html").exists());
+    assert!(root.join("target/vexdoc/lib_util-rs.html").exists());
+    assert!(!root.join("docs").exists());
+    Ok(())
+}
+
+#[test]
+fn output_dir_is_not_documented() -> Result<(), Box<dyn Error>> {
+    let tmp_dir = TempDir::new()?;
+    let root = tmp_dir.path();
+    fs::write(root.join("index.html"), "<!--! Page -->\n<nav></nav>\n<!-- ENDVEXDOC -->\n")?;
+    fs::write(
+        root.join("VexDoc.toml"),
+        r#"ignored_dirs = []
+file_extensions = []
+
+[languages.html]
+multi_comments = ["<!--", "-->"]
+"#,
+    )?;
+
+    // The pages written by the first run would otherwise be documented by the second
+    let conf = DocGenConfig::read_config_at(&root.join("VexDoc.toml"))?;
+    document(conf, vec![root.join("index.html")], false, true)?;
+    assert!(root.join("docs/index-html.html").exists());
+    let conf = DocGenConfig::read_config_at(&root.join("VexDoc.toml"))?;
+    assert_eq!(conf.get_files()?, [root.join("index.html")]);
+    Ok(())
+}
+
+#[test]
+fn missing_source_root_is_a_config_error() -> Result<(), Box<dyn Error>> {
+    let tmp_dir = TempDir::new()?;
+    fs::write(
+        tmp_dir.path().join("VexDoc.toml"),
+        r#"inline_comments = "//"
+multi_comments = ["/*", "*/"]
+ignored_dirs = []
+file_extensions = ["rs"]
+source_roots = ["does-not-exist"]
+"#,
+    )?;
+
+    let conf = DocGenConfig::read_config_at(&tmp_dir.path().join("VexDoc.toml"))?;
+    match conf.get_files() {
+        Err(SubcommandError::UserError { causes, kind: UserErrorKind::Config, .. }) => {
+            assert!(causes.contains("does-not-exist"));
+        }
+        other => panic!("Expected a config error, got {:?}", other),
+    }
+    Ok(())
+}
+
+#[test]
+fn include_and_exclude_globs() -> Result<(), Box<dyn Error>> {
+    let tmp_dir = TempDir::new()?;
+    let root = tmp_dir.path();
+    for dir in ["src/generated", "src/parser/generated", "scripts"] {
+        fs::create_dir_all(root.join(dir))?;
+    }
+    for file in [
+        "src/lib.rs",
+        "src/generated/bindings.rs",
+        "src/parser/generated/tables.rs",
+        "src/parser/lexer.py",
+        "src/parser/lexer_test.py",
+        "scripts/release.py",
+    ] {
+        fs::write(root.join(file), "")?;
+    }
+    fs::write(
+        root.join("VexDoc.toml"),
+        r#"inline_comments = "//"
+multi_comments = ["/*", "*/"]
+ignored_dirs = []
+file_extensions = ["rs", "py"]
+include = ["src/"]
+exclude = ["**/*_test.py", "/src/generated/"]
+"#,
+    )?;
+
+    let conf = DocGenConfig::read_config_at(&root.join("VexDoc.toml"))?;
+    let mut files: Vec<PathBuf> = conf
+        .get_files()?
+        .iter()
+        .map(|f| f.strip_prefix(root).unwrap().to_path_buf())
+        .collect();
+    files.sort();
+
+    // Only the top-level generated dir is anchored, the parser's one is still documented
+    let expected: Vec<PathBuf> = ["src/lib.rs", "src/parser/generated/tables.rs", "src/parser/lexer.py"]
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+    assert_eq!(files, expected);
+    Ok(())
+}
+
+#[test]
+fn deny_invalid_glob() {
+    let conf = DocGenConfig::parse_config(
+        r#"inline_comments = "//"
+multi_comments = ["/*", "*/"]
+ignored_dirs = []
+file_extensions = ["rs"]
+exclude = ["src/{generated,vendored"]
+"#,
+        Path::new("VexDoc.toml"),
+    );
+
+    match conf {
+        Err(SubcommandError::UserError { causes, .. }) => {
+            assert!(causes.contains("Invalid pattern 'src/{generated,vendored' in exclude"));
+        }
+        other => panic!("Expected a config error, got {:?}", other),
+    }
+}
+
+#[test]
+fn respects_ignore_files() -> Result<(), Box<dyn Error>> {
+    let tmp_dir = TempDir::new()?;
+    let root = tmp_dir.path();
+    for dir in ["src/build", "src/vendored", "venv/lib"] {
+        fs::create_dir_all(root.join(dir))?;
+    }
+    for file in [
+        "src/lib.rs",
+        "src/scratch.rs",
+        "src/keep.rs",
+        "src/build/out.rs",
+        "src/vendored/dep.rs",
+        "src/vendored/patched.rs",
+        "venv/lib/site.py",
+    ] {
+        fs::write(root.join(file), "")?;
+    }
+    fs::write(root.join(".gitignore"), "build/\nvenv/\n*.rs\n!lib.rs\n!src/vendored/\n")?;
+    fs::write(root.join("src").join(".ignore"), "!keep.rs\n")?;
+    fs::write(root.join("src").join("vendored").join(".gitignore"), "*.rs\n")?;
+    fs::write(root.join("src").join("vendored").join(".vexdocignore"), "!patched.rs\n")?;
+    let config = |respect: bool| {
+        format!(
+            r#"inline_comments = "//"
+multi_comments = ["/*", "*/"]
+ignored_dirs = []
+file_extensions = ["rs", "py"]
+source_roots = ["src", "venv"]
+respect_gitignore = {}
+"#,
+            respect
+        )
+    };
+
+    let relative_files = |conf: &DocGenConfig| -> Result<Vec<String>, SubcommandError> {
+        let mut files: Vec<String> = conf
+            .get_files()?
+            .iter()
+            .map(|f| f.strip_prefix(root).unwrap().display().to_string())
+            .collect();
+        files.sort();
+        Ok(files)
+    };
+
+    fs::write(root.join("VexDoc.toml"), config(true))?;
+    let conf = DocGenConfig::read_config_at(&root.join("VexDoc.toml"))?;
+    // The root .gitignore applies inside the src and venv source roots, and nested files
+    // can re-include what a parent ignored
+    assert_eq!(relative_files(&conf)?, ["src/keep.rs", "src/lib.rs", "src/vendored/patched.rs"]);
+
+    fs::write(root.join("VexDoc.toml"), config(false))?;
+    let conf = DocGenConfig::read_config_at(&root.join("VexDoc.toml"))?;
+    assert_eq!(relative_files(&conf)?.len(), 7);
+    Ok(())
+}
+
+#[test]
+fn shebang_is_not_a_title() -> Result<(), Box<dyn Error>> {
+    let tmp_dir = TempDir::new()?;
+    let conf = DocGenConfig::parse_config(
+        r##"inline_comments = "#"
+multi_comments = ['"""', '"""']
+ignored_dirs = []
+file_extensions = ["py"]
+"##,
+        Path::new("VexDoc.toml"),
+    )?;
+
+    let script = tmp_dir.path().join("tool.py");
+    fs::write(
+        &script,
+        "#!/usr/bin/env python3\nimport sys\n\n#! Entry Point\n\"\"\"startsummary\nRuns the tool.\nendsummary\"\"\"\ndef main(): pass\n# ENDVEXDOC\n",
+    )?;
+    assert!(create_doc(&script, &conf, tmp_dir.path())?.has_vexdoc);
+
+    // Only the first line can be a shebang
+    assert!(is_shebang("#!/bin/sh"));
+    assert!(is_shebang("#! /usr/bin/env bash"));
+    assert!(!is_shebang("#! Entry Point"));
+    Ok(())
+}
+
+#[test]
+fn custom_markers() -> Result<(), Box<dyn Error>> {
+    let tmp_dir = TempDir::new()?;
+    let conf = DocGenConfig::parse_config(
+        r#"inline_comments = "//"
+multi_comments = ["/*", "*/"]
+ignored_dirs = []
+file_extensions = ["rs"]
+
+[markers]
+title = " @doc"
+start_summary = "SUMMARY"
+end_summary = "/SUMMARY"
+end = "@end"
+"#,
+        Path::new("VexDoc.toml"),
+    )?;
+
+    let source = tmp_dir.path().join("lib.rs");
+    fs::write(
+        &source,
+        r#"//! Crate-level docs that are not VexDoc titles
+//!
+//! ```
+//! let x = 1;
+//! ```
+
+// @doc Custom Section
+/*SUMMARY
+Uses custom markers.
+/SUMMARY*/
+fn f() {}
+// @end
+"#,
+    )?;
+    assert!(create_doc(&source, &conf, tmp_dir.path())?.has_vexdoc);
+
+    let page = read_only_page(tmp_dir.path())?;
+    assert!(page.contains("Custom Section"));
+    assert!(page.contains("Uses custom markers."));
+    assert!(!page.contains("Crate-level docs"));
+    Ok(())
+}
+
+#[test]
+fn multiple_block_comment_pairs() -> Result<(), Box<dyn Error>> {
+    let tmp_dir = TempDir::new()?;
+    let conf = DocGenConfig::parse_config(
+        r##"inline_comments = "#"
+multi_comments = [['"""', '"""'], ["'''", "'''"]]
+ignored_dirs = []
+file_extensions = ["py"]
+"##,
+        Path::new("VexDoc.toml"),
+    )?;
+
+    let source = tmp_dir.path().join("quotes.py");
+    fs::write(
+        &source,
+        r#"#! Double Quotes
+"""startsummary
+Summary in double quotes.
+endsummary"""
+def a(): pass
+# ENDVEXDOC
+
+#! Single Quotes
+''' startsummary
+Summary in single quotes, which may mention """ freely.
+endsummary '''
+def b(): pass
+# ENDVEXDOC
+"#,
+    )?;
+    assert!(create_doc(&source, &conf, tmp_dir.path())?.has_vexdoc);
+
+    let page = read_only_page(tmp_dir.path())?;
+    assert!(page.contains("Summary in double quotes."));
+    assert!(page.contains("Summary in single quotes"));
+    assert!(page.contains("def b(): pass"));
+    Ok(())
+}
+
+#[test]
+fn nested_block_comments() -> Result<(), Box<dyn Error>> {
+    let tmp_dir = TempDir::new()?;
+    let conf = DocGenConfig::parse_config(
+        r#"inline_comments = "--"
+multi_comments = ["{-", "-}"]
+nested_comments = true
+ignored_dirs = []
+file_extensions = ["hs"]
+"#,
+        Path::new("VexDoc.toml"),
+    )?;
+
+    let source = tmp_dir.path().join("Lib.hs");
+    fs::write(
+        &source,
+        r#"--! Nested
+{-startsummary
+An example that is itself commented out:
+{- f = undefined
+endsummary-}
+The real end follows.
+endsummary-}
+f = 1
+-- ENDVEXDOC
+"#,
+    )?;
+    assert!(create_doc(&source, &conf, tmp_dir.path())?.has_vexdoc);
+
+    let page = read_only_page(tmp_dir.path())?;
+    assert!(page.contains("The real end follows."));
+    assert!(page.contains("f = 1"));
+
+    // A stray `-}` closes the summary's own comment, so its end marker is never reached
+    fs::write(&source, "--! Stray\n{-startsummary\nOops -}\nendsummary-}\nf = 1\n-- ENDVEXDOC\n")?;
+    let err = create_doc(&source, &conf, tmp_dir.path()).expect_err("an unbalanced close is an error");
+    assert_eq!(err.code(), Some(crate::codes::ErrorCode::UnterminatedSection));
+    Ok(())
+}
+
+#[test]
+fn line_comment_only_language() -> Result<(), Box<dyn Error>> {
+    let tmp_dir = TempDir::new()?;
+    let conf = DocGenConfig::parse_config(
+        r##"inline_comments = "#"
+ignored_dirs = []
+file_extensions = ["sh"]
+"##,
+        Path::new("VexDoc.toml"),
+    )?;
+
+    let source = tmp_dir.path().join("deploy.sh");
+    fs::write(
+        &source,
+        r##"#!/bin/sh
+# filesummary
+# Deploys the site.
+# endsummary
+
+#! Upload
+# startsummary
+# Copies the build to the server.
+# endsummary
+rsync -a build/ server:/srv
+# ENDVEXDOC
+"##,
+    )?;
+    assert!(create_doc(&source, &conf, tmp_dir.path())?.has_vexdoc);
+
+    let page = read_only_page(tmp_dir.path())?;
+    assert!(page.contains("Deploys the site."));
+    assert!(page.contains("Copies the build to the server."));
+    assert!(page.contains("rsync -a build/ server:/srv"));
+
+    // A line comment summary must be closed before the code starts
+    fs::write(&source, "#! Upload\n# startsummary\n# Copies the build.\nrsync -a build/ server:/srv\n")?;
+    assert!(create_doc(&source, &conf, tmp_dir.path()).is_err());
+    Ok(())
+}
+
+#[test]
+fn block_comment_only_language() -> Result<(), Box<dyn Error>> {
+    let tmp_dir = TempDir::new()?;
+    let conf = DocGenConfig::parse_config(
+        r#"multi_comments = ["<!--", "-->"]
+ignored_dirs = []
+file_extensions = ["html"]
+"#,
+        Path::new("VexDoc.toml"),
+    )?;
+
+    // Keep the source apart from the generated page, which shares its extension
+    let site = TempDir::new()?;
+    let source = site.path().join("index.html");
+    fs::write(
+        &source,
+        r#"<!--! Navigation -->
+<!--startsummary
+The site-wide navigation bar.
+endsummary-->
+<nav></nav>
+<!-- ENDVEXDOC -->
+"#,
+    )?;
+    assert!(create_doc(&source, &conf, tmp_dir.path())?.has_vexdoc);
+
+    let page = read_only_page(tmp_dir.path())?;
+    assert!(page.contains("<h2 id=\"navigation\">Navigation</h2>"));
+    assert!(page.contains("The site-wide navigation bar."));
+    // The markup is shown as source, not rendered as part of the page
+    assert!(page.contains("<pre><code>&lt;nav&gt;&lt;/nav&gt;</code></pre>"));
+    assert!(!page.contains("<nav></nav>"));
+    assert!(!page.contains("ENDVEXDOC"));
+    Ok(())
+}
+
+#[test]
+fn deny_config_without_comments() {
+    let conf = DocGenConfig::parse_config(
+        r#"ignored_dirs = []
+file_extensions = ["txt"]
+"#,
+        Path::new("VexDoc.toml"),
+    );
+
+    match conf {
+        Err(SubcommandError::UserError { causes, .. }) => {
+            assert!(causes.contains("No comment delimiters specified"));
+        }
+        other => panic!("Expected a config error, got {:?}", other),
+    }
+}
+
+#[test]
+fn indented_and_forgiving_blocks() -> Result<(), Box<dyn Error>> {
+    let tmp_dir = TempDir::new()?;
+    let conf = DocGenConfig::parse_config(
+        r#"inline_comments = "//"
+multi_comments = ["/*", "*/"]
+ignored_dirs = []
+file_extensions = ["rs"]
+"#,
+        Path::new("VexDoc.toml"),
+    )?;
+
+    let source = tmp_dir.path().join("point.rs");
+    fs::write(
+        &source,
+        r#"impl Point {
+    //! Length
+
+    // An ordinary comment
+    /*startsummary
+    Distance from the origin.
+    endsummary*/
+    fn length(&self) -> f64 {
+        self.x.hypot(self.y)
+    }
+    // ENDVEXDOC
+
+    //! Origin
+    fn origin() -> Self {
+        Self { x: 0.0, y: 0.0 }
+    }
+    // ENDVEXDOC
+}
+"#,
+    )?;
+    assert!(create_doc(&source, &conf, tmp_dir.path())?.has_vexdoc);
+
+    let page = read_only_page(tmp_dir.path())?;
+    assert!(page.contains("<h2 id=\"length\">Length</h2>"));
+    assert!(page.contains("<p class=\"comment\">Distance from the origin.</p>"));
+    // Code is shown relative to the title's indentation
+    assert!(page.contains("<code>fn length(&amp;self) -&gt; f64 {\n    self.x.hypot(self.y)\n}</code>"));
+    assert!(!page.contains("An ordinary comment"));
+    // A title without a summary goes straight to its code
+    assert!(page.contains("<h2 id=\"origin\">Origin</h2>"));
+    assert!(page.contains("<code>fn origin() -&gt; Self {"));
+    Ok(())
+}
+
+#[test]
+fn plain_summaries_are_escaped() -> Result<(), Box<dyn Error>> {
+    let tmp_dir = TempDir::new()?;
+    let conf = DocGenConfig::parse_config(
+        "inline_comments = \"//\"\nmulti_comments = [\"/*\", \"*/\"]\nignored_dirs = []\nfile_extensions = [\"rs\"]\n",
+        Path::new("VexDoc.toml"),
+    )?;
+    let source = tmp_dir.path().join("stack.rs");
+    fs::write(
+        &source,
+        "//! Stack\n/*startsummary\nWraps a Vec<T> & keeps <b>one</b> [[Stack]] on top.\nendsummary*/\nstruct Stack;\n// ENDVEXDOC\n",
+    )?;
+    create_doc(&source, &conf, tmp_dir.path())?;
+
+    let page = read_only_page(tmp_dir.path())?;
+    assert!(page.contains(
+        "<p class=\"comment\">Wraps a Vec&lt;T&gt; &amp; keeps &lt;b&gt;one&lt;/b&gt; <a href=\"#stack\">Stack</a> on top.</p>"
+    ));
+    Ok(())
+}
+
+#[test]
+fn markers_in_string_literals_are_code() -> Result<(), Box<dyn Error>> {
+    let tmp_dir = TempDir::new()?;
+    let conf = DocGenConfig::parse_config(
+        r##"inline_comments = "//"
+multi_comments = ["/*", "*/"]
+multiline_strings = [['"', '"'], ['r#"', '"#']]
+ignored_dirs = []
+file_extensions = ["rs"]
+"##,
+        Path::new("VexDoc.toml"),
+    )?;
+
+    let source = tmp_dir.path().join("fixture.rs");
+    fs::write(
+        &source,
+        r#"const NOT_A_SECTION: &str = "
+//! Fake Title
+";
+
+//! Fixture
+/*startsummary
+A test fixture.
+endsummary*/
+const FIXTURE: &str = "
+// ENDVEXDOC
+";
+// ENDVEXDOC
+"#,
+    )?;
+    assert!(create_doc(&source, &conf, tmp_dir.path())?.has_vexdoc);
+
+    let page = read_only_page(tmp_dir.path())?;
+    assert!(!page.contains("Fake Title</h2>"));
+    assert!(page.contains("<h2 id=\"fixture\">Fixture</h2>"));
+    assert!(page.contains("const FIXTURE: &amp;str = &quot;\n// ENDVEXDOC\n&quot;;</code>"));
+
+    // Quotes inside raw strings, character literals and comments don't open a string
+    fs::write(
+        &source,
+        r##"const S: &str = r#"say "hi"#;
+const Q: char = '"'; /* a "quote */
+//! After
+fn after() {}
+// ENDVEXDOC
+"##,
+    )?;
+    create_doc(&source, &conf, tmp_dir.path())?;
+    assert!(read_only_page(tmp_dir.path())?.contains("<h2 id=\"after\">After</h2>"));
+
+    // A string still open at the end of the file is reported where it starts
+    fs::write(&source, "//! Before\nfn before() {}\n// ENDVEXDOC\nconst S: &str = \"open\n//! Lost\n")?;
+    let err = create_doc(&source, &conf, tmp_dir.path()).expect_err("an unclosed string is an error");
+    assert_eq!(err.code(), Some(crate::codes::ErrorCode::UnterminatedString));
+    assert_eq!(err.location(), Some((source.as_path(), Some(Span::new(4, 1)))));
+    Ok(())
+}
+
+#[test]
+fn unterminated_block_at_end_of_file() -> Result<(), Box<dyn Error>> {
+    let tmp_dir = TempDir::new()?;
+    let config = r#"inline_comments = "//"
+multi_comments = ["/*", "*/"]
+ignored_dirs = []
+file_extensions = ["rs"]
+"#;
+    let conf = DocGenConfig::parse_config(config, Path::new("VexDoc.toml"))?;
+
+    let source = tmp_dir.path().join("open.rs");
+    fs::write(
+        &source,
+        r#"fn main() {}
+
+//! Forgotten End
+/*startsummary
+Never closed.
+endsummary*/
+fn forgotten() {}
+"#,
+    )?;
+    match create_doc(&source, &conf, tmp_dir.path()) {
+        Err(SubcommandError::UserError { causes, kind, .. }) => {
+            assert!(matches!(kind, UserErrorKind::Annotations));
+            assert!(causes.contains("section 'Forgotten End' starting on line 3 never ends with `// ENDVEXDOC`"));
+        }
+        other => panic!("Expected an annotation error, got {:?}", other),
+    }
+
+    // The lenient mode writes the partial block instead
+    let conf = DocGenConfig::parse_config(&format!("allow_unterminated = true\n{}", config), Path::new("VexDoc.toml"))?;
+    assert!(create_doc(&source, &conf, tmp_dir.path())?.has_vexdoc);
+    let page = read_only_page(tmp_dir.path())?;
+    assert!(page.contains("<code>fn forgotten() {}</code>"));
+    Ok(())
+}
+
+#[test]
+fn errors_carry_line_and_column() -> Result<(), Box<dyn Error>> {
+    // Semantic config errors point at the offending value
+    let conf = DocGenConfig::parse_config(
+        r#"inline_comments = "//"
+multi_comments = ["/*", "*/"]
+ignored_dirs = []
+file_extensions = ["rs", ".py"]
+"#,
+        Path::new("VexDoc.toml"),
+    );
+    match conf {
+        Err(err @ SubcommandError::UserError { .. }) => {
+            let SubcommandError::UserError { causes, span, .. } = &err else { unreachable!() };
+            assert_eq!(*span, Some(Span::new(4, 26)));
+            assert_eq!(causes, "File extension '.py' should not start with a period");
+            assert!(err.to_string().contains("Configuration error in VexDoc.toml:4:26"));
+        }
+        other => panic!("Expected a config error, got {:?}", other),
+    }
+
+    // TOML syntax errors use the span from the toml crate
+    let conf = DocGenConfig::parse_config("ignored_dirs = []\nfile_extensions = = [\"rs\"]\n", Path::new("VexDoc.toml"));
+    match conf {
+        Err(SubcommandError::UserError { span: Some(span), .. }) => assert_eq!(span.line, 2),
+        other => panic!("Expected a config error with a span, got {:?}", other),
+    }
+
+    // Annotation errors point at the marker that opened the block
+    let tmp_dir = TempDir::new()?;
+    let conf = DocGenConfig::parse_config(
+        r#"inline_comments = "//"
+multi_comments = ["/*", "*/"]
+ignored_dirs = []
+file_extensions = ["rs"]
+"#,
+        Path::new("VexDoc.toml"),
+    )?;
+    let source = tmp_dir.path().join("open.rs");
+    fs::write(&source, "mod inner {\n    //! Open\n    fn f() {}\n}\n")?;
+    match create_doc(&source, &conf, tmp_dir.path()) {
+        Err(SubcommandError::UserError { span, .. }) => assert_eq!(span, Some(Span::new(2, 5))),
+        other => panic!("Expected an annotation error, got {:?}", other),
+    }
+    Ok(())
+}
+
+#[test]
+fn collects_errors_from_every_file() -> Result<(), Box<dyn Error>> {
+    let tmp_dir = TempDir::new()?;
+    let mut conf = DocGenConfig::parse_config(
+        r##"inline_comments = "#"
+ignored_dirs = []
+file_extensions = ["sh"]
+"##,
+        Path::new("VexDoc.toml"),
+    )?;
+    conf.set_output_dir(tmp_dir.path().join("docs"))?;
+
+    let first = tmp_dir.path().join("a.sh");
+    let second = tmp_dir.path().join("b.sh");
+    // Two errors in one file: a summary that runs into code, then a section that never ends
+    fs::write(&first, "#! One\n# startsummary\n# text\necho one\n")?;
+    fs::write(&second, "#! Two\n# startsummary\n# endsummary\necho two\n")?;
+
+    match document(conf, vec![second, first], false, true) {
+        Err(err @ SubcommandError::Multiple(_)) => {
+            let SubcommandError::Multiple(errors) = &err else { unreachable!() };
+            let locations: Vec<(String, usize)> = errors
+                .iter()
+                .filter_map(|e| e.location())
+                .map(|(file, span)| (file.file_name().unwrap().to_string_lossy().into_owned(), span.unwrap().line))
+                .collect();
+            assert_eq!(
+                locations,
+                vec![("a.sh".to_string(), 1), ("a.sh".to_string(), 4), ("b.sh".to_string(), 1)]
+            );
+            assert_eq!(err.summary(), "3 errors in 2 files");
+        }
+        other => panic!("Expected several errors, got {:?}", other),
+    }
+    Ok(())
+}
+
+#[test]
+fn renders_code_frames() -> Result<(), Box<dyn Error>> {
+    let tmp_dir = TempDir::new()?;
+    let conf = DocGenConfig::parse_config(
+        r#"inline_comments = "//"
+multi_comments = ["/*", "*/"]
+ignored_dirs = []
+file_extensions = ["rs"]
+"#,
+        Path::new("VexDoc.toml"),
+    )?;
+    let source = tmp_dir.path().join("open.rs");
+    fs::write(&source, "mod inner {\n    //! Open\n    fn f() {}\n}\n")?;
+    let err = create_doc(&source, &conf, tmp_dir.path()).unwrap_err();
+
+    let rendered = crate::diagnostics::Renderer::new(false).render(&err);
+    let expected = format!(
+        "error[VD0020]: section 'Open' starting on line 2 never ends with `// ENDVEXDOC`\n \
+         --> {}:2:5\n  |\n2 |     //! Open\n  |     ^^^\n",
+        source.display()
+    );
+    assert_eq!(rendered, expected);
+    // No escape codes unless colour is asked for
+    assert!(!rendered.contains('\x1b'));
+    assert!(crate::diagnostics::Renderer::new(true).render(&err).contains("\x1b[1;31m"));
+
+    // Frames read the source in the configured encoding, as generation does
+    let conf = DocGenConfig::parse_config(
+        "inline_comments = \"//\"\nignored_dirs = []\nfile_extensions = [\"rs\"]\nencoding = \"windows-1252\"\n",
+        Path::new("VexDoc.toml"),
+    )?;
+    let latin1 = tmp_dir.path().join("latin1.rs");
+    fs::write(&latin1, b"mod inner {\n    //! Caf\xe9\n}\n")?;
+    let err = create_doc(&latin1, &conf, tmp_dir.path()).unwrap_err();
+    let rendered = crate::diagnostics::Renderer::new(false)
+        .with_encoding(conf.fallback_encoding())
+        .render(&err);
+    assert!(rendered.contains("2 |     //! Caf\u{e9}\n"));
+    Ok(())
+}
+
+#[test]
+fn error_codes_and_exit_codes() -> Result<(), Box<dyn Error>> {
+    use crate::codes::{ErrorCode, EXPLANATIONS};
+
+    // Every code is explained once and can be looked up in its short forms
+    for explanation in EXPLANATIONS {
+        let code = explanation.code;
+        assert_eq!(ErrorCode::parse(code.as_str()), Some(code));
+        assert_eq!(ErrorCode::parse(&code.as_str().to_lowercase()), Some(code));
+        assert_eq!(code.explanation().title, explanation.title);
+        assert_eq!(EXPLANATIONS.iter().filter(|e| e.code == code).count(), 1);
+    }
+    assert_eq!(ErrorCode::parse("vd10"), Some(ErrorCode::LeadingPeriod));
+    assert_eq!(ErrorCode::parse("VD9999"), None);
+
+    let conf = DocGenConfig::parse_config(
+        r#"ignored_dirs = []
+file_extensions = [".rs"]
+multi_comments = ["/*"]
+"#,
+        Path::new("VexDoc.toml"),
+    );
+    match conf {
+        Err(err @ SubcommandError::Multiple(_)) => {
+            let SubcommandError::Multiple(errors) = &err else { unreachable!() };
+            let codes: Vec<_> = errors.iter().filter_map(|e| e.code()).collect();
+            assert!(codes.contains(&ErrorCode::LeadingPeriod));
+            assert!(codes.contains(&ErrorCode::IncompletePair));
+            assert_eq!(err.exit_code(), 2);
+        }
+        other => panic!("Expected several config errors, got {:?}", other),
+    }
+
+    let tmp_dir = TempDir::new()?;
+    let conf = DocGenConfig::parse_config(
+        "inline_comments = \"//\"\nignored_dirs = []\nfile_extensions = [\"rs\"]\n",
+        Path::new("VexDoc.toml"),
+    )?;
+    let source = tmp_dir.path().join("open.rs");
+    fs::write(&source, "//! Open\nfn f() {}\n")?;
+    let err = create_doc(&source, &conf, tmp_dir.path()).unwrap_err();
+    assert_eq!(err.code(), Some(ErrorCode::UnterminatedSection));
+    assert_eq!(err.exit_code(), 3);
+    Ok(())
+}
+
+#[test]
+fn check_reports_without_writing() -> Result<(), Box<dyn Error>> {
+    use crate::report::{render, Format};
+
+    let tmp_dir = TempDir::new()?;
+    let mut conf = DocGenConfig::parse_config(
+        "inline_comments = \"//\"\nignored_dirs = []\nfile_extensions = [\"rs\"]\n",
+        Path::new("VexDoc.toml"),
+    )?;
+    let out_dir = tmp_dir.path().join("docs");
+    conf.set_output_dir(out_dir.clone())?;
+    let good = tmp_dir.path().join("good.rs");
+    let bad = tmp_dir.path().join("bad.rs");
+    fs::write(&good, "//! Good\nfn g() {}\n// ENDVEXDOC\n")?;
+    fs::write(&bad, "fn f() {}\n//! Open, \"50%\"\nfn f() {}\n")?;
+
+    let checks = check(&conf, vec![good.clone(), bad.clone()]);
+    assert!(!out_dir.exists(), "check must not write any documentation");
+    assert_eq!(checks.len(), 2);
+    assert!(checks[0].has_vexdoc && checks[0].errors.is_empty());
+    assert_eq!(checks[1].errors.len(), 1);
+
+    let json = render(Format::Json, &checks);
+    assert_eq!(json.lines().count(), 1);
+    assert!(json.contains(r#""code":"VD0020""#));
+    assert!(json.contains(r#""line":2"#));
+
+    // Workflow commands escape `%` and the separators in their properties
+    let github = render(Format::Github, &checks);
+    assert!(github.starts_with("::error file="));
+    assert!(github.contains("bad.rs,line=2,col=1,title=VD0020::section 'Open, \"50%25\"'"));
+
+    let junit = render(Format::Junit, &checks);
+    assert!(junit.contains(r#"tests="2" failures="1""#));
+    assert!(junit.contains(r#"<failure type="VD0020" message="section &apos;Open, &quot;50%&quot;&apos;"#));
+
+    let sarif = render(Format::Sarif, &checks);
+    assert!(sarif.contains(r#""version": "2.1.0""#));
+    assert!(sarif.contains(r#""ruleId": "VD0020""#));
+    Ok(())
+}
+
+#[test]
+fn lints_warn_deny_and_allow() -> Result<(), Box<dyn Error>> {
+    use crate::codes::ErrorCode;
+
+    let tmp_dir = TempDir::new()?;
+    let config = r##"inline_comments = "#"
+ignored_dirs = []
+file_extensions = ["sh"]
+
+[lints]
+summary_punctuation = "warn"
+long_title = "deny"
+max_title_length = 20
+"##;
+    let conf = DocGenConfig::parse_config(config, Path::new("VexDoc.toml"))?;
+    let source = tmp_dir.path().join("deploy.sh");
+    fs::write(
+        &source,
+        "#! Deploy\n# startsummary\n# Copies the build\n# endsummary\nrsync -a build/ srv:/\n# ENDVEXDOC\n\
+         #! Deploy\n# ENDVEXDOC\n\
+         #! Clean\n# startsummary\n# vexdoc-allow(empty_summary)\n# endsummary\n# vexdoc-allow(empty_code)\n# ENDVEXDOC\n",
+    )?;
+    let out = TempDir::new()?;
+    let file = create_doc(&source, &conf, out.path())?;
+    let codes: Vec<_> = file.warnings.iter().filter_map(|w| w.code()).collect();
+    assert_eq!(
+        codes,
+        [ErrorCode::SummaryPunctuation, ErrorCode::DuplicateTitle, ErrorCode::EmptyCode]
+    );
+    assert!(file.warnings.iter().all(|w| w.is_warning() && w.exit_code() == 0));
+    // The directive is not part of the rendered page
+    assert!(!read_only_page(out.path())?.contains("vexdoc-allow"));
+
+    // Denied rules fail the file
+    fs::write(&source, "#! A title that is far too long\necho\n# ENDVEXDOC\n")?;
+    let err = create_doc(&source, &conf, out.path()).unwrap_err();
+    assert_eq!(err.code(), Some(ErrorCode::LongTitle));
+    assert_eq!(err.exit_code(), 3);
+
+    // A misspelt rule in a directive allows nothing, and says so
+    fs::write(&source, "#! Tidy\n# vexdoc-allow(empty-code)\n# ENDVEXDOC\n")?;
+    let file = create_doc(&source, &conf, out.path())?;
+    let codes: Vec<_> = file.warnings.iter().filter_map(|w| w.code()).collect();
+    assert_eq!(codes, [ErrorCode::UnknownLint, ErrorCode::EmptyCode]);
+    assert_eq!(file.warnings[0].location(), Some((source.as_path(), Some(Span::new(2, 16)))));
+    assert_eq!(file.warnings[0].help(), Some("did you mean `empty_code`?"));
+
+    // Unknown rules are config errors
+    let typo = DocGenConfig::parse_config(&format!("{}empty_sumary = \"deny\"\n", config), Path::new("VexDoc.toml"));
+    assert!(matches!(typo, Err(ref e) if e.code() == Some(ErrorCode::ConfigSyntax)));
+    Ok(())
+}
+
+#[test]
+fn markdown_and_plain_summaries() -> Result<(), Box<dyn Error>> {
+    let summary = "/*startsummary\nParses `input` into a *tree*.\n\nSupports:\n- files\n- [stdin](https://example.com)\n\n```rust\nlet tree = parse(\"a\");\n```\nendsummary*/\nfn parse() {}\n// ENDVEXDOC\n";
+    let source_dir = TempDir::new()?;
+    let source = source_dir.path().join("parse.rs");
+    fs::write(&source, format!("//! Parse\n{}", summary))?;
+    let config = "inline_comments = \"//\"\nmulti_comments = [\"/*\", \"*/\"]\nignored_dirs = []\nfile_extensions = [\"rs\"]\n";
+
+    let out = TempDir::new()?;
+    let conf = DocGenConfig::parse_config(&format!("summary_format = \"markdown\"\n{}", config), Path::new("VexDoc.toml"))?;
+    create_doc(&source, &conf, out.path())?;
+    let page = read_only_page(out.path())?;
+    assert!(page.contains("<p>Parses <code>input</code> into a <em>tree</em>.</p>"));
+    assert!(page.contains("<li><a href=\"https://example.com\">stdin</a></li>"));
+    assert!(page.contains("<pre><code class=\"language-rust\">let tree = parse(\"a\");\n</code></pre>"));
+
+    // Plain text is the default, so existing docs keep their look
+    let out = TempDir::new()?;
+    let conf = DocGenConfig::parse_config(config, Path::new("VexDoc.toml"))?;
+    create_doc(&source, &conf, out.path())?;
+    let page = read_only_page(out.path())?;
+    assert!(page.contains("<p class=\"comment\">Parses `input` into a *tree*.  Supports: - files"));
+    Ok(())
+}
+
+#[test]
+fn summary_tags() -> Result<(), Box<dyn Error>> {
+    use crate::codes::ErrorCode;
+
+    let source_dir = TempDir::new()?;
+    let source = source_dir.path().join("calc.py");
+    fs::write(
+        &source,
+        r#"#! Divide
+"""startsummary
+Divides two numbers.
+@deprecated use `div` instead
+@param a the dividend
+@param b the divisor,
+  which must not be zero
+@returns the quotient
+@errors ZeroDivisionError when `b` is 0
+@example
+    @cached
+    divide(6, 3)
+@see div
+@since 0.2
+@parm c typo
+endsummary"""
+def divide(a, b):
+    return a / b
+# ENDVEXDOC
+"#,
+    )?;
+    let conf = DocGenConfig::parse_config(
+        "summary_format = \"markdown\"\nignored_dirs = []\nfile_extensions = []\n[languages.py]\ninline_comments = \"#\"\nmulti_comments = ['\"\"\"', '\"\"\"']\n",
+        Path::new("VexDoc.toml"),
+    )?;
+    let out = TempDir::new()?;
+    let file = create_doc(&source, &conf, out.path())?;
+    let page = read_only_page(out.path())?;
+
+    assert!(page.contains("<div class=\"deprecated\"><strong>Deprecated.</strong> use <code>div</code> instead</div>"));
+    assert!(page.contains("<div class=\"comment markdown\"><p>Divides two numbers.</p>"));
+    assert!(page.contains("<tr><td><code>a</code></td><td>the dividend</td></tr>"));
+    assert!(page.contains("<tr><td><code>b</code></td><td>the divisor, which must not be zero</td></tr>"));
+    assert!(page.contains("<h4>Returns</h4><p>the quotient</p>"));
+    assert!(page.contains("<h4>Errors</h4><p>ZeroDivisionError when <code>b</code> is 0</p>"));
+    // Decorators inside an example are code, not tags
+    assert!(page.contains("<h4>Example</h4><pre><code>@cached\ndivide(6, 3)</code></pre>"));
+    assert!(page.contains("<strong>See also:</strong> div"));
+    assert!(page.contains("<strong>Since:</strong> 0.2"));
+    assert!(page.contains("<code>@parm</code> c typo"));
+
+    let codes: Vec<_> = file.warnings.iter().filter_map(|w| w.code()).collect();
+    assert_eq!(codes, [ErrorCode::UnknownTag]);
+    assert_eq!(file.warnings[0].location().and_then(|(_, span)| span), Some(Span::new(15, 1)));
+    Ok(())
+}
+
+#[test]
+fn cross_references_and_backlinks() -> Result<(), Box<dyn Error>> {
+    use crate::codes::ErrorCode;
+
+    let project = TempDir::new()?;
+    let config = "summary_format = \"markdown\"\ninline_comments = \"//\"\nmulti_comments = [\"/*\", \"*/\"]\nignored_dirs = []\nfile_extensions = [\"rs\"]\n";
+    let mut conf = DocGenConfig::parse_config(config, Path::new("VexDoc.toml"))?;
+    conf.root = project.path().into();
+    fs::create_dir(project.path().join("src"))?;
+    let parser = project.path().join("src/parser.rs");
+    let render = project.path().join("render.rs");
+    fs::write(
+        &parser,
+        "//! Parse Input\n/*startsummary\nReads the input. See [[Tokenize]] and [[render.rs#Render Output|the renderer]].\nAlso [[Missing Thing]].\nendsummary*/\nfn parse() {}\n// ENDVEXDOC\n//! Tokenize\nfn tokenize() {}\n// ENDVEXDOC\n",
+    )?;
+    fs::write(
+        &render,
+        "//! Render Output\n/*startsummary\nWrites what [[src/parser.rs#Parse Input]] read, unlike `[[not a link]]`.\n@see [[Tokenize]]\nendsummary*/\nfn render() {}\n// ENDVEXDOC\n",
+    )?;
+
+    let out = TempDir::new()?;
+    let results = generate(&conf, &[&parser, &render], out.path(), &ProgressBar::hidden(), false);
+    let parser_page = fs::read_to_string(out.path().join("src_parser-rs.html"))?;
+    let render_page = fs::read_to_string(out.path().join("render-rs.html"))?;
+
+    assert!(parser_page.contains("<h2 id=\"parse-input\">Parse Input</h2>"));
+    assert!(parser_page.contains("<a href=\"#tokenize\">Tokenize</a>"));
+    assert!(parser_page.contains("<a href=\"render-rs.html#render-output\">the renderer</a>"));
+    assert!(parser_page.contains("<span class=\"unresolved\">Missing Thing</span>"));
+    assert!(render_page.contains("<a href=\"src_parser-rs.html#parse-input\">Parse Input</a>"));
+    assert!(render_page.contains("<code>[[not a link]]</code>"));
+
+    // Each target lists what refers to it, after its code
+    assert!(parser_page.contains(
+        "<p class=\"backlinks\"><strong>Referenced by:</strong> <a href=\"render-rs.html#render-output\">Render Output (render.rs)</a></p>"
+    ));
+    assert!(parser_page.contains(
+        "<p class=\"backlinks\"><strong>Referenced by:</strong> <a href=\"#parse-input\">Parse Input</a></p>"
+    ));
+
+    let warnings: Vec<_> = results.iter().map(|r| r.as_ref().map(|f| f.warnings.len()).unwrap_or(0)).collect();
+    assert_eq!(warnings, [1, 1]);
+    let missing = &results[0].as_ref().map_err(|e| e.to_string())?.warnings[0];
+    assert_eq!(missing.code(), Some(ErrorCode::UnresolvedReference));
+    assert_eq!(missing.location().and_then(|(_, span)| span), Some(Span::new(4, 6)));
+    let elsewhere = &results[1].as_ref().map_err(|e| e.to_string())?.warnings[0];
+    assert!(elsewhere.help().is_some_and(|h| h.contains("`[[src/parser.rs#Tokenize]]`")));
+
+    // Denying the lint makes unresolved references errors
+    let mut strict = DocGenConfig::parse_config(
+        &format!("{}[lints]\nunresolved_reference = \"deny\"\n", config),
+        Path::new("VexDoc.toml"),
+    )?;
+    strict.root = project.path().into();
+    let checks = check(&strict, vec![parser, render]);
+    assert_eq!(checks[0].errors.len(), 1);
+    assert_eq!(checks[1].errors.len(), 1);
+    assert!(checks.iter().all(|c| c.warnings.is_empty()));
+    Ok(())
+}
+
+#[test]
+fn derived_titles_and_signatures() -> Result<(), Box<dyn Error>> {
+    let source_dir = TempDir::new()?;
+    let rust = source_dir.path().join("shapes.rs");
+    fs::write(
+        &rust,
+        "//!\n/// Adds two numbers\n#[inline]\npub fn my_awesome_function(a: i32, b: i32) -> i32 {\n    a + b\n}\n// ENDVEXDOC\n\
+         //! Point\npub struct Point<T: Copy> {\n    x: T,\n}\n// ENDVEXDOC\n\
+         //!\nstatic int parse(const char *input,\n                 size_t length);\n// ENDVEXDOC\n\
+         //! Vec<T> helpers\nfn first() {}\n// ENDVEXDOC\n\
+         //!\nassert!(a < b && b < c);\n// ENDVEXDOC\n",
+    )?;
+    let conf = DocGenConfig::parse_config(
+        "inline_comments = \"//\"\nmulti_comments = [\"/*\", \"*/\"]\nignored_dirs = []\nfile_extensions = [\"rs\"]\n",
+        Path::new("VexDoc.toml"),
+    )?;
+    let out = TempDir::new()?;
+    create_doc(&rust, &conf, out.path())?;
+    let page = read_only_page(out.path())?;
+    assert!(page.contains("<h2 id=\"my-awesome-function\">my_awesome_function</h2>"));
+    assert!(page.contains(
+        "<pre class=\"signature\"><code>pub fn my_awesome_function(a: i32, b: i32) -&gt; i32</code></pre>"
+    ));
+    // Explicit titles are kept, and still get a signature
+    assert!(page.contains("<h2 id=\"point\">Point</h2>"));
+    assert!(page.contains("<code>pub struct Point&lt;T: Copy&gt;</code>"));
+    // Parameters over several lines are joined into one
+    assert!(page.contains("<h2 id=\"parse\">parse</h2>"));
+    assert!(page.contains("<code>static int parse(const char *input, size_t length)</code>"));
+    // Titles are text, however much they look like markup
+    assert!(page.contains(">Vec&lt;T&gt; helpers</h2>"));
+    assert!(page.contains(">assert!(a &lt; b &amp;&amp; b &lt; c)</h2>"));
+
+    let python = source_dir.path().join("area.py");
+    fs::write(
+        &python,
+        "#!\n@property\ndef area(self) -> float:\n    return 1.0\n# ENDVEXDOC\n\
+         #! Timeout\nTIMEOUT = 30\ndef unrelated():\n    pass\n# ENDVEXDOC\n",
+    )?;
+    let conf = DocGenConfig::parse_config(
+        "inline_comments = \"#\"\nmulti_comments = ['\"\"\"', '\"\"\"']\nignored_dirs = []\nfile_extensions = [\"py\"]\n",
+        Path::new("VexDoc.toml"),
+    )?;
+    let out = TempDir::new()?;
+    create_doc(&python, &conf, out.path())?;
+    let page = read_only_page(out.path())?;
+    assert!(page.contains("<h2 id=\"area\">area</h2>"));
+    assert!(page.contains("<code>def area(self) -&gt; float</code>"));
+    // Only the first line of code is a signature, not a declaration further down
+    assert!(!page.contains("<code>def unrelated()</code>"));
+    Ok(())
+}
+
+#[test]
+fn implicit_block_ends() -> Result<(), Box<dyn Error>> {
+    let conf = DocGenConfig::parse_config(
+        r##"inline_comments = "//"
+multi_comments = ["/*", "*/"]
+block_end = "braces"
+ignored_dirs = []
+file_extensions = ["rs", "txt"]
+
+[languages.py]
+inline_comments = "#"
+multi_comments = ['"""', '"""']
+block_end = "indent"
+
+[languages.txt]
+inline_comments = "//"
+block_end = "next-title"
+"##,
+        Path::new("VexDoc.toml"),
+    )?;
+    let source_dir = TempDir::new()?;
+
+    let rust = source_dir.path().join("add.rs");
+    fs::write(
+        &rust,
+        "//! Add\n/*startsummary\nAdds.\nendsummary*/\n#[inline]\nfn add(a: i32, b: i32) -> i32 {\n    let brace = '{';\n    a + b // }\n}\n\nfn not_documented() {}\n\
+         //! Limit\nconst LIMIT: usize = 10;\nfn also_not_documented() {}\n\
+         //! Notes\n/*startsummary\nProse only.\nendsummary*/\n//! Tail\nfn tail() {\n",
+    )?;
+    let out = TempDir::new()?;
+    let file = create_doc(&rust, &conf, out.path())?;
+    let page = read_only_page(out.path())?;
+    assert!(page.contains("<pre><code>#[inline]\nfn add(a: i32, b: i32) -&gt; i32 {\n    let brace = '{';\n    a + b // }\n}</code></pre>"));
+    assert!(page.contains("<pre><code>const LIMIT: usize = 10;</code></pre>"));
+    assert!(!page.contains("documented"));
+    // A section of prose only has no code block, and no empty_code warning
+    assert!(page.contains("<p class=\"comment\">Prose only.</p><h2 id=\"tail\">"));
+    assert!(page.contains("<pre><code>fn tail() {</code></pre>"));
+    assert!(file.warnings.is_empty());
+
+    let python = source_dir.path().join("area.py");
+    fs::write(
+        &python,
+        "#! Area\n@property\ndef area(self):\n    return (\n1.0)\n\ndef helper():\n    pass\n#! Second\nclass Second:\n    x = 1\n\
+         #! Timeout\nTIMEOUT = 30\ndef unrelated():\n    pass\n",
+    )?;
+    let out = TempDir::new()?;
+    create_doc(&python, &conf, out.path())?;
+    let page = read_only_page(out.path())?;
+    assert!(page.contains("<pre><code>@property\ndef area(self):\n    return (\n1.0)</code></pre>"));
+    assert!(!page.contains("helper"));
+    assert!(page.contains("<pre><code>class Second:\n    x = 1</code></pre>"));
+    // A statement with no indented body ends at the next line back at its indentation
+    assert!(page.contains("<pre><code>TIMEOUT = 30</code></pre>"));
+    assert!(!page.contains("unrelated"));
+
+    let notes = source_dir.path().join("notes.txt");
+    fs::write(&notes, "//! One\nfirst\n\n//! Two\nsecond\n")?;
+    let out = TempDir::new()?;
+    create_doc(&notes, &conf, out.path())?;
+    let page = read_only_page(out.path())?;
+    assert!(page.contains("<pre><code>first</code></pre>"));
+    assert!(page.contains("<pre><code>second</code></pre>"));
+    Ok(())
+}
+
+#[test]
+fn source_encodings() -> Result<(), Box<dyn Error>> {
+    use crate::codes::ErrorCode;
+
+    let config = "inline_comments = \"//\"\nmulti_comments = [\"/*\", \"*/\"]\nignored_dirs = []\nfile_extensions = [\"c\"]\n";
+    let conf = DocGenConfig::parse_config(config, Path::new("VexDoc.toml"))?;
+    let source_dir = TempDir::new()?;
+    let utf16 = |text: &str, little_endian: bool| -> Vec<u8> {
+        text.encode_utf16()
+            .flat_map(|unit| if little_endian { unit.to_le_bytes() } else { unit.to_be_bytes() })
+            .collect()
+    };
+
+    // A byte order mark is dropped and CRLF line endings come through as plain lines
+    let bom = source_dir.path().join("bom.c");
+    fs::write(&bom, "\u{FEFF}//! Bom\r\n/*startsummary\r\nHello.\r\nendsummary*/\r\nint a;\r\n// ENDVEXDOC\r\n")?;
+    let out = TempDir::new()?;
+    let file = create_doc(&bom, &conf, out.path())?;
+    let page = read_only_page(out.path())?;
+    assert!(page.contains("<h2 id=\"bom\">Bom</h2>"));
+    assert!(page.contains("<pre><code>int a;</code></pre>"));
+    assert!(!page.contains('\r'));
+    assert!(file.warnings.is_empty());
+
+    // UTF-16 is read with its byte order mark, or recognised without one
+    let text = "//! Wide\nint naïve;\n// ENDVEXDOC\n";
+    for (name, bytes) in [
+        ("le.c", [vec![0xFF, 0xFE], utf16(text, true)].concat()),
+        ("be.c", utf16(text, false)),
+    ] {
+        let path = source_dir.path().join(name);
+        fs::write(&path, bytes)?;
+        let out = TempDir::new()?;
+        create_doc(&path, &conf, out.path())?;
+        assert!(read_only_page(out.path())?.contains("<pre><code>int naïve;</code></pre>"));
+    }
+
+    // Latin-1 is a warning naming the file, unless `encoding` says how to read it
+    let latin1 = source_dir.path().join("latin1.c");
+    fs::write(&latin1, b"//! Cafe\nconst char *name = \"caf\xe9\";\n// ENDVEXDOC\n")?;
+    let out = TempDir::new()?;
+    let file = create_doc(&latin1, &conf, out.path())?;
+    assert!(read_only_page(out.path())?.contains("caf\u{FFFD}"));
+    assert_eq!(file.warnings.len(), 1);
+    assert_eq!(file.warnings[0].code(), Some(ErrorCode::LossyDecoding));
+    assert_eq!(file.warnings[0].location(), Some((latin1.as_path(), Some(Span::new(2, 24)))));
+
+    let conf = DocGenConfig::parse_config(&format!("{}encoding = \"latin1\"\n", config), Path::new("VexDoc.toml"))?;
+    let out = TempDir::new()?;
+    let file = create_doc(&latin1, &conf, out.path())?;
+    assert!(read_only_page(out.path())?.contains("&quot;café&quot;"));
+    assert!(file.warnings.is_empty());
+
+    let err = DocGenConfig::parse_config(&format!("{}encoding = \"ansi\"\n", config), Path::new("VexDoc.toml"))
+        .expect_err("unknown encodings are config errors");
+    assert_eq!(err.code(), Some(ErrorCode::UnknownEncoding));
+    Ok(())
+}
+
+#[test]
+fn section_visibility() -> Result<(), Box<dyn Error>> {
+    let config = "inline_comments = \"//\"\nmulti_comments = [\"/*\", \"*/\"]\nignored_dirs = []\nfile_extensions = [\"rs\"]\n";
+    let mut conf = DocGenConfig::parse_config(config, Path::new("VexDoc.toml"))?;
+    let source_dir = TempDir::new()?;
+    let mixed = source_dir.path().join("mixed.rs");
+    fs::write(
+        &mixed,
+        "//! Parsing\nfn parse() {}\n// ENDVEXDOC\n\
+         //! [internal] Retry policy\nconst RETRIES: u32 = 3;\n// ENDVEXDOC\n\
+         //! Cache\n/*startsummary\nSee [[Retry policy]].\n@internal\nendsummary*/\nstruct Cache;\n// ENDVEXDOC\n\
+         //! Client\n/*startsummary\nFailed calls follow the [[Retry policy]].\nendsummary*/\nstruct Client;\n// ENDVEXDOC\n",
+    )?;
+    let notes = source_dir.path().join("notes.rs");
+    fs::write(
+        &notes,
+        "/*filesummary\nOn-call notes.\n@internal\nendsummary*/\n//! Pager\nfn page() {}\n// ENDVEXDOC\n",
+    )?;
+
+    // A public build is the default
+    assert_eq!(conf.visibility, Visibility::Public);
+
+    // Every section is shown at `all`, with the internal ones marked
+    conf.set_visibility(Visibility::All);
+    let out = TempDir::new()?;
+    create_doc(&mixed, &conf, out.path())?;
+    let page = read_only_page(out.path())?;
+    assert!(page.contains("<h2 id=\"retry-policy\">Retry policy <span class=\"visibility\">internal</span></h2>"));
+    assert!(page.contains("<h2 id=\"cache\">Cache <span class=\"visibility\">internal</span></h2>"));
+    assert!(page.contains("<h2 id=\"parsing\">Parsing</h2>"));
+    assert!(!page.contains("@internal"));
+
+    assert!(page.contains("Failed calls follow the <a href=\"#retry-policy\">Retry policy</a>."));
+
+    // A public build leaves internal sections out, and links to them become plain text rather
+    // than unresolved references, even when those are denied
+    conf.set_visibility(Visibility::Public);
+    conf.lints = DocGenConfig::parse_config(
+        &format!("{}[lints]\nunresolved_reference = \"deny\"\n", config),
+        Path::new("VexDoc.toml"),
+    )?
+    .lints;
+    let out = TempDir::new()?;
+    let file = create_doc(&mixed, &conf, out.path())?;
+    let page = read_only_page(out.path())?;
+    assert!(file.errors.is_empty() && file.warnings.is_empty());
+    assert!(page.contains("Parsing"));
+    assert!(page.contains("<p class=\"comment\">Failed calls follow the Retry policy.</p>"));
+    assert!(!page.contains("retry-policy") && !page.contains("class=\"unresolved\"") && !page.contains("RETRIES"));
+    assert!(!page.contains("Cache"));
+
+    // A file that is internal throughout gets no page, and a page from an earlier build is removed
+    conf.root = source_dir.path().to_path_buf();
+    let stale = out.path().join(conf.page_name(&notes));
+    fs::write(&stale, "On-call notes.")?;
+    let file = create_doc(&notes, &conf, out.path())?;
+    assert!(file.hidden);
+    assert!(!stale.exists());
+
+    conf.set_visibility(Visibility::Internal);
+    let out = TempDir::new()?;
+    let file = create_doc(&mixed, &conf, out.path())?;
+    let page = read_only_page(out.path())?;
+    assert!(!file.hidden);
+    assert!(page.contains("Retry policy") && page.contains("Cache"));
+    assert!(!page.contains("Parsing") && !page.contains("Client"));
+    Ok(())
+}
@@ -1,7 +1,9 @@
 use std::error::Error;
 use std::fmt;
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+use crate::codes::ErrorCode;
 
 // Error handling for VexDoc
 
@@ -17,11 +19,50 @@ pub enum SubcommandError {
     GenerationWriteError(io::Error),
     /// User error - usually configuration or annotation problems
     UserError {
+        code: ErrorCode,
         causes: String,
         source: Option<Box<dyn Error + Send + Sync>>,
         kind: UserErrorKind,
         file: PathBuf,
+        /// Where in `file` the problem is, when it is known
+        span: Option<Span>,
+        /// How to fix the problem, when there is a specific suggestion
+        help: Option<String>,
     },
+    /// Several errors, collected so they can all be fixed in one pass
+    Multiple(Vec<SubcommandError>),
+    /// An error that was already printed in another form, such as a `vexdoc check --format json`
+    /// report, and only decides the exit code
+    Reported(Box<SubcommandError>),
+}
+
+/// A 1-based line and column in a source or config file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    pub fn new(line: usize, column: usize) -> Self {
+        Self { line, column }
+    }
+
+    /// Converts a byte offset into `text` to a line and column, counting columns in characters
+    pub fn from_offset(text: &str, offset: usize) -> Self {
+        let before = &text[..offset.min(text.len())];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        Self {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
 }
 
 #[derive(Debug)]
@@ -30,18 +71,43 @@ pub enum UserErrorKind {
     Config,
     /// Documentation annotation problems (missing summary, malformed blocks, etc.)
     Annotations,
+    /// Command line arguments that make no sense, such as an unknown error code
+    Usage,
+    /// A lint finding that is reported but does not fail the run
+    Warning,
 }
 
 /// Represents a validation error with its corresponding suggestion
 #[derive(Debug)]
 pub struct ValidationError {
+    pub code: ErrorCode,
     pub message: String,
     pub suggestion: String,
+    /// Where in the config file the offending key or value is
+    pub span: Option<Span>,
 }
 
 impl ValidationError {
-    pub fn new(message: String, suggestion: String) -> Self {
-        Self { message, suggestion }
+    pub fn new(code: ErrorCode, message: String, suggestion: String) -> Self {
+        Self { code, message, suggestion, span: None }
+    }
+
+    pub fn with_span(mut self, span: Option<Span>) -> Self {
+        self.span = span;
+        self
+    }
+
+    /// Turns this into an error for the config file at `file`
+    pub fn into_error(self, file: &Path) -> SubcommandError {
+        SubcommandError::UserError {
+            code: self.code,
+            causes: self.message,
+            source: None,
+            kind: UserErrorKind::Config,
+            file: file.into(),
+            span: self.span,
+            help: Some(self.suggestion),
+        }
     }
 }
 
@@ -69,11 +135,16 @@ impl Error for SubcommandError {
             // Deref the box twice to get the actual error
             Self::GenerationError(e) => Some(&**e),
             Self::GenerationWriteError(e) => Some(e),
+            Self::Multiple(_) => None,
+            Self::Reported(e) => e.source(),
             Self::UserError {
+                code: _,
                 causes: _,
                 source: cause,
                 kind: _,
                 file: _,
+                span: _,
+                help: _,
             } => match cause {
                 Some(e) => Some(&**e),
                 None => None,
@@ -97,41 +168,187 @@ impl fmt::Display for SubcommandError {
             Self::GenerationWriteError(e) => {
                 write!(f, "Failed to write documentation files: {}. Check write permissions in the docs/ directory.", e)
             }
+            Self::Multiple(errors) => {
+                for error in errors {
+                    writeln!(f, "{}\n", error)?;
+                }
+                write!(f, "{}", self.summary())
+            }
+            Self::Reported(e) => write!(f, "{}", e),
             Self::UserError {
+                code: _,
                 causes,
                 source: _,
                 kind,
                 file,
-            } => match kind {
-                UserErrorKind::Config => write!(
-                    f,
-                    "Configuration error in {}: {}\n\nSuggested fixes:\n{}",
-                    file.display(),
-                    self.get_solution_hint(),
-                    causes
-                ),
-                UserErrorKind::Annotations => write!(
-                    f,
-                    "Annotation error in {}: {}\n\nSuggested fixes:\n{}",
-                    file.display(),
-                    self.get_solution_hint(),
-                    causes
-                ),
-            },
+                span,
+                help,
+            } => {
+                // Rendered as `path:line:col` so editors and terminals can jump to it
+                let location = match span {
+                    Some(span) => format!("{}:{}", file.display(), span),
+                    None => file.display().to_string(),
+                };
+                match kind {
+                    UserErrorKind::Config => write!(
+                        f,
+                        "Configuration error in {}: {}\n\nSuggested fixes:\n{}",
+                        location,
+                        self.get_solution_hint(),
+                        causes
+                    )?,
+                    UserErrorKind::Annotations => write!(
+                        f,
+                        "Annotation error in {}: {}\n\nSuggested fixes:\n{}",
+                        location,
+                        self.get_solution_hint(),
+                        causes
+                    )?,
+                    UserErrorKind::Usage => write!(f, "{}", causes)?,
+                    UserErrorKind::Warning => write!(f, "Warning in {}: {}", location, causes)?,
+                }
+                match help {
+                    Some(help) => write!(f, "\n{}", help),
+                    None => Ok(()),
+                }
+            }
         }
     }
 }
 
 impl SubcommandError {
+    /// Combines the errors from several files or blocks, sorted by path and line
+    ///
+    /// Returns `Ok` when there are none, and a lone error as it is.
+    pub fn from_errors(errors: Vec<SubcommandError>) -> Result<(), SubcommandError> {
+        let mut errors: Vec<SubcommandError> = errors.into_iter().flat_map(Self::into_errors).collect();
+        match errors.len() {
+            0 => Ok(()),
+            1 => Err(errors.remove(0)),
+            _ => {
+                // Errors without a location keep their order, ahead of the located ones
+                errors.sort_by(|a, b| a.location().cmp(&b.location()));
+                Err(Self::Multiple(errors))
+            }
+        }
+    }
+
+    /// Splits a collection back into its errors, so each can be reported on its own
+    pub fn into_errors(self) -> Vec<SubcommandError> {
+        match self {
+            Self::Multiple(errors) => errors,
+            e => vec![e],
+        }
+    }
+
+    /// The stable code identifying this kind of failure, or `None` for a collection of errors
+    pub fn code(&self) -> Option<ErrorCode> {
+        match self {
+            Self::InitError(e) if e.kind() == io::ErrorKind::AlreadyExists => Some(ErrorCode::ConfigExists),
+            Self::InitError(_) => Some(ErrorCode::ConfigNotCreated),
+            Self::FileReadError(_) => Some(ErrorCode::FileUnreadable),
+            Self::GenerationError(_) => Some(ErrorCode::GenerationFailed),
+            Self::GenerationWriteError(_) => Some(ErrorCode::OutputNotWritten),
+            Self::UserError { code, .. } => Some(*code),
+            Self::Multiple(_) => None,
+            Self::Reported(e) => e.code(),
+        }
+    }
+
+    /// What went wrong, in one line for errors that are not about a specific file
+    ///
+    /// Errors about a file use the first line of their causes.
+    pub fn message(&self) -> String {
+        match self {
+            Self::InitError(e) if e.kind() == io::ErrorKind::NotFound => {
+                format!("could not create new config file at requested location: {}", e)
+            }
+            Self::InitError(e) => format!("could not create new config file: {}", e),
+            Self::FileReadError(e) => format!("could not read file: {}", e),
+            Self::GenerationError(e) => format!("could not generate documentation for files: {}", e),
+            Self::GenerationWriteError(e) => format!("failed to write documentation files: {}", e),
+            Self::Multiple(_) => format!("found {}", self.summary()),
+            Self::Reported(e) => e.message(),
+            Self::UserError { causes, .. } => causes.lines().next().unwrap_or_default().to_string(),
+        }
+    }
+
+    /// How to fix the problem, when there is a suggestion
+    pub fn help(&self) -> Option<&str> {
+        match self {
+            Self::InitError(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                Some("rerun with --force to overwrite the existing config file")
+            }
+            Self::FileReadError(_) => Some("verify file paths and permissions"),
+            Self::GenerationWriteError(_) => Some("ensure the output directory is writable"),
+            Self::Reported(e) => e.help(),
+            Self::UserError { help, .. } => help.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Whether this is only a warning, which is reported without failing the run
+    pub fn is_warning(&self) -> bool {
+        matches!(self, Self::UserError { kind: UserErrorKind::Warning, .. })
+    }
+
+    /// The process exit code for this error's class
+    ///
+    /// 1 is a usage error (as for unparseable arguments), 2 a config error, 3 an annotation
+    /// error, 4 a file system error and 5 any other generation failure. When several errors
+    /// are collected, the highest of their codes is used.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::UserError { kind, .. } => match kind {
+                UserErrorKind::Usage => 1,
+                UserErrorKind::Config => 2,
+                UserErrorKind::Annotations => 3,
+                UserErrorKind::Warning => 0,
+            },
+            Self::InitError(_) | Self::FileReadError(_) | Self::GenerationWriteError(_) => 4,
+            Self::GenerationError(_) => 5,
+            Self::Multiple(errors) => errors.iter().map(|e| e.exit_code()).max().unwrap_or(1),
+            Self::Reported(e) => e.exit_code(),
+        }
+    }
+
+    /// The file and position an error points at, if it has one
+    pub fn location(&self) -> Option<(&Path, Option<Span>)> {
+        match self {
+            Self::UserError { file, span, .. } => Some((file, *span)),
+            _ => None,
+        }
+    }
+
+    /// A one-line count of the errors and the files they are in, e.g. `3 errors in 2 files`
+    pub fn summary(&self) -> String {
+        match self {
+            Self::Multiple(errors) => {
+                let mut files: Vec<&Path> = errors.iter().filter_map(|e| e.location()).map(|(f, _)| f).collect();
+                files.dedup();
+                match files.len() {
+                    0 | 1 => format!("{} errors", errors.len()),
+                    n => format!("{} errors in {} files", errors.len(), n),
+                }
+            }
+            Self::Reported(e) => e.summary(),
+            _ => "1 error".to_string(),
+        }
+    }
+
     fn get_solution_hint(&self) -> &'static str {
         match self {
             Self::InitError(_) => "Make sure you have write permissions in the current directory",
             Self::FileReadError(_) => "Verify file paths and permissions",
             Self::GenerationError(_) => "Check your VexDoc.toml configuration",
             Self::GenerationWriteError(_) => "Ensure the docs/ directory is writable",
+            Self::Multiple(_) => "Fix each of the errors above",
+            Self::Reported(e) => e.get_solution_hint(),
             Self::UserError { kind, .. } => match kind {
                 UserErrorKind::Config => "Fix the configuration file format",
                 UserErrorKind::Annotations => "Check your documentation block syntax",
+                UserErrorKind::Usage => "Check the command line arguments",
+                UserErrorKind::Warning => "Fix the documentation or allow the lint",
             },
         }
     }
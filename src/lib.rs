@@ -1,78 +1,220 @@
-//! # VexDoc
-/*startsummary
+/*filesummary
 A fast documentation generator that extracts inline comments and generates HTML docs.
 endsummary*/
-//! 
-//! ```toml
-//! # Single-line comment marker (like // or #)
-//! inline_comments = "//"
-//! 
-//! # Multi-line comment delimiters
-//! multi_comments = ["/*", "*/"]
-//! 
-//! # Directories to skip
-//! ignored_dirs = ["target", "node_modules", ".git"]
-//! 
-//! # File types to process
-//! file_extensions = ["rs", "py", "c", "h"]
-//! ```
-//! 
-//! ## Writing Documentation
-//! 
-//! The format is intentionally simple. Just wrap your code with special comments:
-//! 
-//! ```rust
-//! //! My Awesome Function
-//! /*startsummary
-//! This function does something really cool. It takes some input,
-//! processes it through several steps, and returns a useful result.
-//! 
-//! The function handles edge cases gracefully and provides clear
-//! error messages when things go wrong.
-//! endsummary*/
-//! fn my_awesome_function(input: &str) -> Result<String, Error> {
-//!     // Implementation here
-//!     Ok(input.to_uppercase())
-//! }
-//! // ENDVEXDOC
-//! ```
-//! 
-//! That's it! VexDoc will find this block, extract the title and description,
-//! and generate a nice HTML page with syntax highlighting.
+#![doc = r##"
+# VexDoc
+
+```toml
+# Single-line comment marker (like // or #)
+inline_comments = "//"
+
+# Multi-line comment delimiters
+multi_comments = ["/*", "*/"]
+
+# Directories to skip
+ignored_dirs = ["target", "node_modules", ".git"]
+
+# File types to process
+file_extensions = ["rs", "py", "c", "h"]
+```
+
+## Writing Documentation
+
+The format is intentionally simple. Just wrap your code with special comments:
+
+```text
+//! My Awesome Function
+/*startsummary
+This function does something really cool. It takes some input,
+processes it through several steps, and returns a useful result.
+
+The function handles edge cases gracefully and provides clear
+error messages when things go wrong.
+endsummary*/
+fn my_awesome_function(input: &str) -> Result<String, Error> {
+    // Implementation here
+    Ok(input.to_uppercase())
+}
+// ENDVEXDOC
+```
+
+That's it! VexDoc will find this block, extract the title and description,
+and generate a nice HTML page with syntax highlighting.
+"##]
 
 pub mod cli;
+pub mod codes;
+pub mod diagnostics;
 pub mod docgen;
 pub mod errors;
+pub mod report;
+
+use std::path::PathBuf;
 
 use crate::cli::{VexDocArgs, VexDocSubcommands};
-use crate::docgen::{document, DocGenConfig};
-use crate::errors::SubcommandError;
+use crate::codes::{ErrorCode, EXPLANATIONS};
+use crate::diagnostics::Renderer;
+use crate::docgen::{check, detect, document, presets, DocGenConfig, FileCheck, Visibility};
+use crate::errors::{SubcommandError, UserErrorKind};
+use crate::report::Format;
 
 /// Runs the main VexDoc application logic
 pub fn run(args: VexDocArgs) -> Result<(), SubcommandError> {
     match args.subcommands {
+        VexDocSubcommands::Explain(explainargs) => {
+            let Some(code) = &explainargs.code else {
+                for explanation in EXPLANATIONS {
+                    println!("{}  {}", explanation.code, explanation.title);
+                }
+                return Ok(());
+            };
+            match ErrorCode::parse(code) {
+                Some(code) => println!("{}", code.explanation()),
+                None => {
+                    return Err(SubcommandError::UserError {
+                        code: ErrorCode::UnknownCode,
+                        causes: format!("unknown error code '{}'", code),
+                        source: None,
+                        kind: UserErrorKind::Usage,
+                        file: PathBuf::new(),
+                        span: None,
+                        help: Some("run `vexdoc explain` without a code to list every code".into()),
+                    })
+                }
+            }
+        }
         VexDocSubcommands::Init(initargs) => {
+            let mut presets = match &initargs.preset {
+                Some(list) => presets::parse_presets(list)?,
+                None => Vec::new(),
+            };
+            let mut ignored_dirs = presets::DEFAULT_IGNORED_DIRS.to_vec();
+            if initargs.detect {
+                let detection = detect::detect(&initargs.dir).map_err(SubcommandError::FileReadError)?;
+                if detection.languages.is_empty() && presets.is_empty() {
+                    return Err(SubcommandError::UserError {
+                        code: ErrorCode::NothingDetected,
+                        causes: "no files with a known comment syntax were found".into(),
+                        source: None,
+                        kind: UserErrorKind::Config,
+                        file: PathBuf::new(),
+                        span: None,
+                        help: Some("use --preset to pick the languages by hand".into()),
+                    });
+                }
+                for (preset, count) in &detection.languages {
+                    println!("Detected {} ({} files)", preset.name, count);
+                    if !presets.iter().any(|p| p.name == preset.name) {
+                        presets.push(preset);
+                    }
+                }
+                ignored_dirs = detection.ignored_dirs;
+            }
             // TODO: figure out how to avoid clone.
-            if let Err(e) = DocGenConfig::create_config(initargs.dir.clone()) {
+            if let Err(e) =
+                DocGenConfig::create_config(initargs.dir.clone(), &presets, &ignored_dirs, initargs.force)
+            {
                 return Err(SubcommandError::InitError(e));
             }
             // output should come after the action, so that error propagation happens before we
             // tell the user anything
-            println!(
-                "Created new, empty configuration file in {}",
-                initargs.dir.display()
-            );
+            let names: Vec<&str> = presets.iter().map(|p| p.name).collect();
+            if names.is_empty() {
+                println!(
+                    "Created new configuration file in {}; pass --preset or --detect to configure your languages",
+                    initargs.dir.display()
+                );
+            } else {
+                println!(
+                    "Created new configuration file for {} in {}",
+                    names.join(", "),
+                    initargs.dir.display()
+                );
+            }
         }
         VexDocSubcommands::Generate(genargs) => {
-            let conf = DocGenConfig::read_config()?;
+            let mut conf = match &args.config {
+                Some(path) => DocGenConfig::read_config_at(path)?,
+                None => DocGenConfig::read_config()?,
+            };
+            if let Some(out_dir) = genargs.out_dir {
+                conf.set_output_dir(out_dir)
+                    .map_err(SubcommandError::FileReadError)?;
+            }
+            conf.set_visibility(genargs.visibility);
             if !genargs.quiet {
                 println!("Beginning documentation");
             }
-            if genargs.files.len() == 0 {
-                let files = conf.get_files()?;
-                document(conf, files, genargs.verbose, genargs.quiet)?;
+            let renderer = Renderer::for_stderr().with_encoding(conf.fallback_encoding());
+            let files = if genargs.files.is_empty() {
+                conf.get_files()?
             } else {
-                document(conf, genargs.files, genargs.verbose, genargs.quiet)?;
+                genargs.files
+            };
+            // Printed here, where the config says how to read the sources shown in code frames
+            document(conf, files, genargs.verbose, genargs.quiet).map_err(|e| {
+                eprint!("{}", renderer.render(&e));
+                SubcommandError::Reported(Box::new(e))
+            })?;
+        }
+        VexDocSubcommands::Check(checkargs) => {
+            let format = checkargs.format;
+            let conf = match &args.config {
+                Some(path) => DocGenConfig::read_config_at(path),
+                None => DocGenConfig::read_config(),
+            };
+            let mut encoding = None;
+            let checks = conf.and_then(|mut conf| {
+                // Every section is checked, whatever a build would publish
+                conf.set_visibility(Visibility::All);
+                encoding = conf.fallback_encoding();
+                let files = if checkargs.files.is_empty() {
+                    conf.get_files()?
+                } else {
+                    checkargs.files
+                };
+                Ok(check(&conf, files))
+            });
+            let checks = match checks {
+                Ok(checks) => checks,
+                // Reports include config errors too, so CI shows them like any other problem
+                Err(e) if format != Format::Human => {
+                    let errors = e.into_errors();
+                    let path = errors
+                        .iter()
+                        .find_map(|e| e.location())
+                        .map_or_else(|| PathBuf::from("VexDoc.toml"), |(p, _)| p.into());
+                    vec![FileCheck { path, has_vexdoc: false, errors, warnings: Vec::new(), hidden: false }]
+                }
+                Err(e) => return Err(e),
+            };
+            let checked = checks.len();
+            let warnings = checks.iter().map(|c| c.warnings.len()).sum::<usize>();
+            if format == Format::Human {
+                let renderer = Renderer::for_stderr().with_encoding(encoding);
+                for warning in checks.iter().flat_map(|c| &c.warnings) {
+                    eprintln!("{}", renderer.render(warning));
+                }
+            } else {
+                print!("{}", report::render(format, &checks));
+            }
+            let errors = checks.into_iter().flat_map(|c| c.errors).collect();
+            match SubcommandError::from_errors(errors) {
+                Ok(()) if format == Format::Human => {
+                    let plural = |n: usize| if n == 1 { "" } else { "s" };
+                    if warnings == 0 {
+                        println!("Checked {} file{}: no problems found", checked, plural(checked));
+                    } else {
+                        println!("Checked {} file{}: {} warning{}", checked, plural(checked), warnings, plural(warnings));
+                    }
+                }
+                Ok(()) => {}
+                Err(e) if format == Format::Human => {
+                    eprint!("{}", Renderer::for_stderr().with_encoding(encoding).render(&e));
+                    return Err(SubcommandError::Reported(Box::new(e)));
+                }
+                // The report already lists every problem, so only the exit code is left
+                Err(e) => return Err(SubcommandError::Reported(Box::new(e))),
             }
         }
     }
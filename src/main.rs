@@ -1,59 +1,26 @@
-//! VexDoc - The main entry point
-/*startsummary
+/*filesummary
 The main entry point for the VexDoc documentation generator.
 endsummary*/
 
 use std::error::Error;
-use std::io;
 use std::process;
 
-use vexdoc::{cli::VexDocArgs, errors::SubcommandError, run};
+use vexdoc::{cli::VexDocArgs, diagnostics::Renderer, run};
 
 /// Main function - parses args and runs the appropriate subcommand
 fn main() {
     let args: VexDocArgs = argh::from_env();
+    let debug = args.debug;
     let mut exit_code = 0;
     // we only care about Err values because the actual results are all side effects
     
     if let Err(err) = run(args) {
-        exit_code = 1;
-        dbg!(err.source());
-        dbg!(&err);
-        match &err {
-            SubcommandError::InitError(ref e) => match e.kind() {
-                io::ErrorKind::AlreadyExists => {
-                    eprintln!("vexdoc: could not create new config file: {}", e);
-                    eprintln!("Delete the existing config file to generate a new one");
-                }
-                io::ErrorKind::NotFound => {
-                    eprintln!(
-                        "vexdoc: could not create new config file at requested location: {}",
-                        e
-                    );
-                }
-                _ => eprintln!("vexdoc: could not create new config file: {}", e),
-            },
-            SubcommandError::FileReadError(ref e) => {
-                eprintln!("vexdoc: could not read config file: {}", e,);
-            }
-            SubcommandError::GenerationError(ref e) => {
-                eprintln!("vexdoc: could not generate documentation for files: {}", e);
-            }
-            SubcommandError::GenerationWriteError(ref e) => {
-                eprintln!("vexdoc: {}: {}", &err, e);
-            }
-            SubcommandError::UserError {
-                causes,
-                source: _,
-                kind: _,
-                file: _,
-            } => {
-                eprintln!("vexdoc: an error has occurred at runtime: {}", &err);
-                for i in causes.lines() {
-                    eprintln!("caused by: {}", i);
-                }
-            }
+        exit_code = err.exit_code();
+        if debug {
+            dbg!(err.source());
+            dbg!(&err);
         }
+        eprint!("{}", Renderer::for_stderr().render(&err));
     }
     process::exit(exit_code);
 }
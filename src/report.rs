@@ -0,0 +1,260 @@
+/*filesummary
+Formats the results of `vexdoc check` for CI: JSON lines, SARIF, JUnit XML and GitHub Actions annotations.
+People get the same diagnostics as every other command, so the human format is rendered by `diagnostics` instead.
+endsummary*/
+
+use std::env;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde_json::json;
+
+use crate::codes::{ErrorCode, EXPLANATIONS};
+use crate::docgen::FileCheck;
+
+/// How `vexdoc check` prints what it found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Human,
+    /// One JSON object per line, per problem
+    Json,
+    Sarif,
+    Junit,
+    /// `::error` workflow commands, shown as annotations on pull requests
+    Github,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "human" => Ok(Format::Human),
+            "json" => Ok(Format::Json),
+            "sarif" => Ok(Format::Sarif),
+            "junit" => Ok(Format::Junit),
+            "github" => Ok(Format::Github),
+            _ => Err(format!(
+                "unknown format '{}', expected one of human, json, sarif, junit, github",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Format::Human => "human",
+            Format::Json => "json",
+            Format::Sarif => "sarif",
+            Format::Junit => "junit",
+            Format::Github => "github",
+        })
+    }
+}
+
+/// One problem, with everything the machine-readable formats need
+struct Diagnostic {
+    file: String,
+    line: Option<usize>,
+    column: Option<usize>,
+    code: Option<ErrorCode>,
+    /// `error` or `warning`
+    severity: &'static str,
+    message: String,
+    help: Option<String>,
+}
+
+impl Diagnostic {
+    fn code(&self) -> &'static str {
+        self.code.map_or("error", ErrorCode::as_str)
+    }
+}
+
+/// Paths are always written with `/`, relative to where vexdoc was run when they are inside it
+fn display_path(path: &Path) -> String {
+    let current_dir = env::current_dir().unwrap_or_default();
+    let path = path
+        .strip_prefix(&current_dir)
+        .or_else(|_| path.strip_prefix("./"))
+        .unwrap_or(path);
+    path.display().to_string().replace('\\', "/")
+}
+
+fn diagnostics(check: &FileCheck) -> impl Iterator<Item = Diagnostic> + '_ {
+    check.errors.iter().chain(&check.warnings).map(|err| {
+        // Errors that don't know their file, such as a failed read, belong to the file being checked
+        let (file, span) = err.location().unwrap_or((&check.path, None));
+        Diagnostic {
+            file: display_path(file),
+            line: span.map(|s| s.line),
+            column: span.map(|s| s.column),
+            code: err.code(),
+            severity: if err.is_warning() { "warning" } else { "error" },
+            message: err.message(),
+            help: err.help().map(str::to_string),
+        }
+    })
+}
+
+/// Renders the results of `vexdoc check` in a machine-readable format
+///
+/// `Format::Human` is not handled here; it renders nothing.
+pub fn render(format: Format, checks: &[FileCheck]) -> String {
+    match format {
+        Format::Human => String::new(),
+        Format::Json => checks
+            .iter()
+            .flat_map(diagnostics)
+            .map(|d| {
+                let object = json!({
+                    "file": d.file,
+                    "line": d.line,
+                    "column": d.column,
+                    "code": d.code(),
+                    "severity": d.severity,
+                    "message": d.message,
+                    "help": d.help,
+                });
+                format!("{}\n", object)
+            })
+            .collect(),
+        Format::Sarif => sarif(checks),
+        Format::Junit => junit(checks),
+        Format::Github => checks.iter().flat_map(diagnostics).map(|d| github(&d)).collect(),
+    }
+}
+
+/// A SARIF 2.1.0 log, as read by GitHub code scanning and most static analysis dashboards
+fn sarif(checks: &[FileCheck]) -> String {
+    let rules: Vec<_> = EXPLANATIONS
+        .iter()
+        .map(|e| {
+            json!({
+                "id": e.code.as_str(),
+                "shortDescription": { "text": e.title },
+                "fullDescription": { "text": e.explanation },
+            })
+        })
+        .collect();
+    let results: Vec<_> = checks
+        .iter()
+        .flat_map(diagnostics)
+        .map(|d| {
+            let mut region = json!({});
+            if let Some(line) = d.line {
+                region["startLine"] = json!(line);
+            }
+            if let Some(column) = d.column {
+                region["startColumn"] = json!(column);
+            }
+            let message = match &d.help {
+                Some(help) => format!("{}\nhelp: {}", d.message, help),
+                None => d.message.clone(),
+            };
+            json!({
+                "ruleId": d.code(),
+                "level": d.severity,
+                "message": { "text": message },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": d.file },
+                        "region": region,
+                    }
+                }],
+            })
+        })
+        .collect();
+    let log = json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "vexdoc",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules,
+                }
+            },
+            "results": results,
+        }],
+    });
+    format!("{:#}\n", log)
+}
+
+/// A JUnit XML report with one test case per checked file, failing when the file has errors
+///
+/// Warnings don't fail a test case, so they are left out.
+fn junit(checks: &[FileCheck]) -> String {
+    let failures = checks.iter().filter(|c| !c.errors.is_empty()).count();
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str(&format!(
+        "<testsuites name=\"vexdoc\" tests=\"{0}\" failures=\"{1}\">\n  <testsuite name=\"vexdoc check\" tests=\"{0}\" failures=\"{1}\">\n",
+        checks.len(),
+        failures
+    ));
+    for check in checks {
+        let name = xml_escape(&display_path(&check.path));
+        if check.errors.is_empty() {
+            out.push_str(&format!("    <testcase name=\"{}\" classname=\"vexdoc\"/>\n", name));
+            continue;
+        }
+        out.push_str(&format!("    <testcase name=\"{}\" classname=\"vexdoc\">\n", name));
+        for d in diagnostics(check).filter(|d| d.severity == "error") {
+            let mut location = d.file.clone();
+            if let (Some(line), Some(column)) = (d.line, d.column) {
+                location.push_str(&format!(":{}:{}", line, column));
+            }
+            let mut text = format!("{}: {}", location, d.message);
+            if let Some(help) = &d.help {
+                text.push_str(&format!("\nhelp: {}", help));
+            }
+            out.push_str(&format!(
+                "      <failure type=\"{}\" message=\"{}\">{}</failure>\n",
+                d.code(),
+                xml_escape(&d.message),
+                xml_escape(&text)
+            ));
+        }
+        out.push_str("    </testcase>\n");
+    }
+    out.push_str("  </testsuite>\n</testsuites>\n");
+    out
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// A GitHub Actions `::error` or `::warning` workflow command
+fn github(d: &Diagnostic) -> String {
+    let mut properties = format!("file={}", github_property(&d.file));
+    if let Some(line) = d.line {
+        properties.push_str(&format!(",line={}", line));
+    }
+    if let Some(column) = d.column {
+        properties.push_str(&format!(",col={}", column));
+    }
+    properties.push_str(&format!(",title={}", d.code()));
+    let mut message = d.message.clone();
+    if let Some(help) = &d.help {
+        message.push_str(&format!("\nhelp: {}", help));
+    }
+    format!("::{} {}::{}\n", d.severity, properties, github_data(&message))
+}
+
+/// Escapes the message of a workflow command, which ends at the first newline
+fn github_data(text: &str) -> String {
+    text.replace('%', "%25").replace('\r', "%0D").replace('\n', "%0A")
+}
+
+/// Escapes a `key=value` property of a workflow command, where `,` and `:` are separators
+fn github_property(text: &str) -> String {
+    github_data(text).replace(':', "%3A").replace(',', "%2C")
+}